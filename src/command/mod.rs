@@ -42,20 +42,32 @@ pub enum Command {
     PasteSelection,
     CutSelection,
 
+    // VWS profiles
+    CopyVWSProfile,
+    PasteVWSProfile,
+
     // history
     HistoryUndo,
     HistoryRedo,
+    ToggleHistoryPanel,
 
     // I/O
     IOOpen,
+    IOOpenUfo,
     IOSave,
     IOSaveAs,
     IOFlatten,
     IOExport,
 
+    // font navigation
+    NextGlyph,
+    PreviousGlyph,
+    GlyphPicker,
+
     // view modes
     TogglePointLabels,
     TogglePreviewMode,
+    ToggleBooleanPreview,
 
     // console
     ToggleConsole,
@@ -1,8 +1,48 @@
 use MFEKmath::{Piecewise, VWSSettings, variable_width_stroke};
-use glifparser::{VWSContour, glif::{self, MFEKContour, MFEKOutline, MFEKPointData, VWSHandle}};
+use glifparser::{VWSContour, glif::{self, InterpolationType, MFEKContour, MFEKOutline, MFEKPointData, VWSHandle}};
 
 use super::ContourOperation;
 
+// The left and right offsets at `t` along segment `seg`, going linearly from the handle at its
+// start to the one at its end.
+fn offsets_at(vws: &VWSContour, seg: usize, t: f64) -> (f64, f64) {
+    let start = &vws.handles[seg];
+    let end = vws.handles.get(seg + 1).unwrap_or(start);
+    (
+        start.left_offset + (end.left_offset - start.left_offset) * t,
+        start.right_offset + (end.right_offset - start.right_offset) * t,
+    )
+}
+
+/// `vws`, made for a contour of `segments` segments, spread over one of `to_segments` segments and
+/// `to_points` points. Positions are measured in segments, so a handle a third of the way along the
+/// new contour takes the width found a third of the way along the old one.
+pub fn resample(vws: &VWSContour, segments: usize, to_segments: usize, to_points: usize) -> VWSContour {
+    let mut vws = vws.clone();
+    let last = vws.handles.last().cloned().unwrap_or(VWSHandle {
+        left_offset: 10.,
+        right_offset: 10.,
+        tangent_offset: 0.,
+        interpolation: InterpolationType::Linear,
+    });
+    vws.handles.resize(vws.handles.len().max(segments + 1), last);
+
+    let scale = if segments == 0 { 0. } else { to_segments as f64 / segments as f64 };
+    let handles = (0..=to_points).map(|idx| {
+        let pos = if scale == 0. { 0. } else { idx.min(to_segments) as f64 / scale };
+        let seg = (pos + 1e-6).floor().max(0.) as usize;
+        let t = (pos - seg as f64).max(0.);
+        if seg >= segments || t < 1e-6 {
+            vws.handles[seg.min(segments)].clone()
+        } else {
+            let (left_offset, right_offset) = offsets_at(&vws, seg, t);
+            VWSHandle { left_offset, right_offset, tangent_offset: 0., interpolation: vws.handles[seg].interpolation }
+        }
+    }).collect();
+
+    VWSContour { handles, ..vws }
+}
+
 impl ContourOperation for VWSContour {
     fn build(&self, contour: &MFEKContour<MFEKPointData>) -> MFEKOutline<MFEKPointData>
     {
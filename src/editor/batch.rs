@@ -0,0 +1,110 @@
+//! Batch export of many glyphs in one process. Each glyph gets its own Editor, but every UFO's
+//! layercontents.plist is read once before the first glyph and written once after the last.
+
+use super::Editor;
+use super::font;
+use super::export::{merge_layercontents_plist, read_layercontents_plist, write_layercontents_plist};
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
+
+use glifparser::glif::{MFEKGlif, MFEKPointData};
+use mfek_ipc::IPCInfo;
+use plist::Value as PlistValue;
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Is this input to --export meant for `export_batch` rather than a single .glif?
+pub fn is_batch_input(input: &str) -> bool {
+    Path::new(input).is_dir() || input.contains(|c| c == '*' || c == '?' || c == '[')
+}
+
+/// Lists the .glif files in the default layer of a UFO, in contents.plist order.
+fn ufo_glif_paths(ufo: &Path) -> Result<Vec<PathBuf>, MFEKglifError> {
+    Ok(font::read_contents_plist(ufo)?.into_iter().map(|(_name, path)| path).collect())
+}
+
+fn glob_glif_paths(pattern: &str) -> Result<Vec<PathBuf>, MFEKglifError> {
+    let paths = glob::glob(pattern).map_err(|e| MFEKglifError::BadGlob { pattern: pattern.to_string(), reason: e.to_string() })?;
+    Ok(paths.filter_map(|p| p.map_err(|e|log::warn!("Skipping unreadable path: {}", e)).ok()).collect())
+}
+
+struct GlyphReport {
+    path: PathBuf,
+    result: Result<usize, MFEKglifError>,
+}
+
+/// Loads and exports one glyph, returning the number of flattened layers written.
+fn export_one(path: &Path, layercontents: &mut HashMap<PathBuf, Option<PlistValue>>) -> Result<usize, MFEKglifError> {
+    let path = path.to_path_buf();
+    let glif: MFEKGlif<MFEKPointData> = glifparser::read_from_filename(&path).map_err(|e| MFEKglifError::GlifRead { path: path.clone(), reason: format!("{:?}", e) })?.into();
+
+    let mut editor = Editor::new();
+    editor.set_glyph(glif);
+    // We don't want metrics guidelines here, and spawning MFEKmetadata per glyph is slow.
+    editor.ipc_info = Some(IPCInfo::from_glif_path("MFEKglif".to_string(), &path));
+
+    let (export, font) = match panic::catch_unwind(AssertUnwindSafe(|| editor.export_glif_layers())) {
+        Ok(result) => result?,
+        Err(_) => return Err(MFEKglifError::Panicked(path)),
+    };
+
+    if let Some(font) = font {
+        if !layercontents.contains_key(&font) {
+            let current = read_layercontents_plist(&font)?;
+            layercontents.insert(font.clone(), current);
+        }
+        let current = layercontents.get_mut(&font).unwrap();
+        *current = Some(merge_layercontents_plist(&export.layers, current.take()));
+    }
+
+    Ok(export.layers.len())
+}
+
+/// Exports every glyph of a UFO, or every .glif matching a glob. Prints a line per glyph and a
+/// summary, and returns the process exit code: non-zero if any glyph failed.
+pub fn export_batch(input: &str) -> i32 {
+    let paths = if Path::new(input).is_dir() {
+        ufo_glif_paths(Path::new(input))
+    } else {
+        glob_glif_paths(input)
+    };
+
+    let paths = match paths {
+        Ok(paths) => paths,
+        Err(e) => {
+            messages::emit(Message::Error { error: &e });
+            return e.exit_code()
+        }
+    };
+
+    let mut layercontents: HashMap<PathBuf, Option<PlistValue>> = HashMap::new();
+    let mut reports = vec![];
+    for path in paths {
+        let result = export_one(&path, &mut layercontents);
+        match &result {
+            Ok(layers) => messages::emit(Message::GlyphExported { path: &path, layers: *layers }),
+            Err(error) => messages::emit(Message::GlyphFailed { path: &path, error }),
+        }
+        reports.push(GlyphReport { path, result });
+    }
+
+    let mut failed_plists = 0;
+    for (font, plist) in layercontents {
+        if let Some(plist) = plist {
+            match write_layercontents_plist(&font, &plist) {
+                Ok(path) => messages::emit(Message::PlistMerged { path: &path }),
+                Err(e) => { messages::emit(Message::Error { error: &e }); failed_plists += 1; }
+            }
+        }
+    }
+
+    let failed: Vec<_> = reports.iter().filter(|r| r.result.is_err()).collect();
+    messages::emit(Message::BatchFinished { exported: reports.len() - failed.len(), total: reports.len() });
+    for report in &failed {
+        log::error!("Failed to export {}", report.path.display());
+    }
+
+    if failed.is_empty() && failed_plists == 0 { 0 } else { 1 }
+}
@@ -0,0 +1,221 @@
+//! Boolean operations between contours of the active layer, remove overlap, and the pieces they share
+//! with merging layers: contours become Skia paths, go through a `PathOp`, and come back as plain
+//! contours. Contours that a boolean doesn't touch are kept as they are, operations and all.
+
+use glifparser::{Outline, PointType};
+use glifparser::glif::{MFEKContour, MFEKOutline, MFEKPointData};
+use glifparser::outline::skia::{FromSkiaPath, ToSkiaPaths};
+use skulpin::skia_safe::{Path, PathFillType, PathOp, Rect};
+
+use std::collections::BTreeSet;
+
+use crate::contour_operations;
+use super::Editor;
+
+/// Whether the contour is drawn open, i.e. starts with a move. An empty contour isn't closed either.
+pub fn is_open(contour: &MFEKContour<MFEKPointData>) -> bool {
+    contour.inner.first().map(|p| p.ptype == PointType::Move).unwrap_or(true)
+}
+
+/// Whether the contour has a closed outline to bring to a boolean. Open contours without an
+/// operation to close them are left out, as in prepare_export.
+pub fn is_closed_outline(contour: &MFEKContour<MFEKPointData>) -> bool {
+    contour.operation.is_some() || !is_open(contour)
+}
+
+/// The closed outline of `contours`, with their contour operations built.
+pub fn closed_path(contours: &[&MFEKContour<MFEKPointData>]) -> Path {
+    let built: MFEKOutline<MFEKPointData> = contours.iter().flat_map(|c| contour_operations::build(c)).collect();
+    built.to_skia_paths(None).closed.unwrap_or(Path::new())
+}
+
+pub fn to_contours(path: &Path) -> MFEKOutline<MFEKPointData> {
+    Outline::from_skia_path(path).iter().map(|c| c.into()).collect()
+}
+
+/// Applies `op` to `a` and `b`. None if Skia can't.
+pub fn path_op(a: &Path, b: &Path, op: PathOp) -> Option<Path> {
+    a.op(b, op).and_then(|p| p.as_winding())
+}
+
+fn bounds_overlap(a: &Rect, b: &Rect) -> bool {
+    a.left <= b.right && b.left <= a.right && a.top <= b.bottom && b.top <= a.bottom
+}
+
+/// Whether the filled outlines of `a` and `b` share any area. Bounds rule out most pairs before
+/// Skia has to intersect them.
+fn overlaps(a: &Path, b: &Path) -> bool {
+    bounds_overlap(a.bounds(), b.bounds())
+        && path_op(a, b, PathOp::Intersect).map(|p| !p.is_empty()).unwrap_or(false)
+}
+
+/// Splits `contours` into groups whose outlines overlap, directly or through other contours of the
+/// group; a counter overlaps the contour it is in. Contours in different groups can't affect each
+/// other in a boolean. Groups and their members are in the order of `contours`.
+pub fn overlap_groups(contours: &[&MFEKContour<MFEKPointData>]) -> Vec<Vec<usize>> {
+    let paths: Vec<Path> = contours.iter().map(|c| closed_path(&[*c])).collect();
+
+    // Connected components of the overlap graph, as a group number per contour.
+    let mut group: Vec<usize> = (0..contours.len()).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..contours.len() {
+            for j in i + 1..contours.len() {
+                if group[i] != group[j] && overlaps(&paths[i], &paths[j]) {
+                    let (from, to) = (group[i].max(group[j]), group[i].min(group[j]));
+                    group.iter_mut().filter(|g| **g == from).for_each(|g| *g = to);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // Each group is numbered after its first member.
+    (0..contours.len()).filter(|&i| group[i] == i)
+        .map(|i| (i..contours.len()).filter(|&j| group[j] == i).collect())
+        .collect()
+}
+
+/// `contours` as one path, filled by `fill_type`, resolved into contours that don't overlap. With
+/// the winding fill a glyph is drawn with, counters stay holes.
+fn resolve(contours: &[&MFEKContour<MFEKPointData>], fill_type: PathFillType) -> MFEKOutline<MFEKPointData> {
+    let mut path = closed_path(contours);
+    path.set_fill_type(fill_type);
+    match path_op(&path, &Path::new(), PathOp::Union) {
+        Some(result) => to_contours(&result),
+        None => contours.iter().map(|c| (*c).clone()).collect(),
+    }
+}
+
+fn fold(contours: &[&MFEKContour<MFEKPointData>], op: PathOp) -> MFEKOutline<MFEKPointData> {
+    let mut path = closed_path(&contours[..1]);
+    for contour in &contours[1..] {
+        if let Some(result) = path_op(&path, &closed_path(&[*contour]), op) {
+            path = result;
+        }
+    }
+    to_contours(&path)
+}
+
+/// The result of `op` between `contours`, in order. A difference cuts the first contour with the
+/// others; the other operations combine them all. A union fills the contours as the glyph does, so
+/// the counters among them stay open, and an exclusion fills them even-odd.
+fn combine_contours(contours: &[&MFEKContour<MFEKPointData>], op: PathOp) -> MFEKOutline<MFEKPointData> {
+    match op {
+        PathOp::Difference => {
+            let (base, cutters) = (contours[0], &contours[1..]);
+            let (touching, apart): (Vec<&MFEKContour<MFEKPointData>>, Vec<&MFEKContour<MFEKPointData>>) =
+                cutters.iter().copied().partition(|c| overlap_groups(&[base, *c]).len() == 1);
+            let mut ret = if touching.is_empty() {
+                vec![base.clone()]
+            } else {
+                path_op(&closed_path(&[base]), &closed_path(&touching), op).map(|p| to_contours(&p)).unwrap_or_default()
+            };
+            ret.extend(apart.into_iter().cloned());
+            ret
+        }
+        PathOp::Intersect => fold(contours, op),
+        _ => {
+            let fill_type = if matches!(op, PathOp::XOR) { PathFillType::EvenOdd } else { PathFillType::Winding };
+            overlap_groups(contours).into_iter().flat_map(|members| {
+                let members: Vec<&MFEKContour<MFEKPointData>> = members.into_iter().map(|i| contours[i]).collect();
+                if members.len() == 1 { vec![members[0].clone()] } else { resolve(&members, fill_type) }
+            }).collect()
+        }
+    }
+}
+
+impl Editor {
+    /// The contours of the active layer with a selected point, in order.
+    pub fn selected_contours(&self) -> BTreeSet<usize> {
+        let mut ret: BTreeSet<usize> = self.selected.iter().map(|(ci, _)| *ci).collect();
+        if let (Some(ci), Some(_)) = (self.contour_idx, self.point_idx) {
+            ret.insert(ci);
+        }
+        ret
+    }
+
+    fn selected_closed_contours(&self) -> Vec<usize> {
+        self.with_active_layer(|layer| {
+            self.selected_contours().into_iter().filter(|&ci| ci < layer.outline.len() && is_closed_outline(&layer.outline[ci])).collect()
+        })
+    }
+
+    /// Whether contour_boolean has anything to work on: two or more selected closed contours.
+    pub fn can_combine_contours(&self) -> bool {
+        self.selected_closed_contours().len() > 1
+    }
+
+    /// Applies `op` between the selected closed contours of the active layer; for a difference, the
+    /// first of them is cut by the rest. Selected contours that no other overlaps are left as they
+    /// are. The results take the place of the contours they came from and are selected. Generates a
+    /// history entry, and returns false without one if there was nothing to combine.
+    pub fn contour_boolean(&mut self, op: PathOp) -> bool {
+        if self.is_modifying() || !self.can_combine_contours() { return false }
+        let selected = self.selected_closed_contours();
+
+        let results = self.with_active_layer(|layer| {
+            let contours: Vec<&MFEKContour<MFEKPointData>> = selected.iter().map(|&ci| &layer.outline[ci]).collect();
+            combine_contours(&contours, op)
+        });
+
+        let description = match op {
+            PathOp::Union => "Union of contours.",
+            PathOp::Difference => "Subtracted contours.",
+            PathOp::Intersect => "Intersection of contours.",
+            _ => "Exclusion of contours.",
+        };
+        self.begin_layer_modification(description);
+        let first = selected[0];
+        let count = results.len();
+        self.with_active_layer_mut(|layer| {
+            for &ci in selected.iter().rev() {
+                layer.outline.remove(ci);
+            }
+            layer.outline.splice(first..first, results.clone());
+        });
+        self.end_layer_modification();
+
+        self.contour_idx = None;
+        self.point_idx = None;
+        self.selected.clear();
+        for ci in first..first + count {
+            let len = self.with_active_layer(|layer| layer.outline[ci].inner.len());
+            self.selected.extend((0..len).map(|pi| (ci, pi)));
+        }
+        true
+    }
+
+    /// Unites the overlapping closed contours of the active layer, as export does with a union
+    /// layer, so no part of the glyph is covered twice. Contours that overlap nothing, and open
+    /// contours, are left as they are and where they are; each union takes the place of the first
+    /// contour in it. Generates a history entry.
+    pub fn remove_overlap(&mut self) {
+        if self.is_modifying() { return }
+
+        self.begin_layer_modification("Removed overlap.");
+        self.with_active_layer_mut(|layer| {
+            let closed_idx: Vec<usize> = (0..layer.outline.len()).filter(|&ci| is_closed_outline(&layer.outline[ci])).collect();
+            let closed: Vec<&MFEKContour<MFEKPointData>> = closed_idx.iter().map(|&ci| &layer.outline[ci]).collect();
+
+            // What each contour becomes. A group's union takes the place of its first contour, so
+            // everything else keeps its index.
+            let mut placed: Vec<MFEKOutline<MFEKPointData>> = layer.outline.iter().map(|c| vec![c.clone()]).collect();
+            for members in overlap_groups(&closed) {
+                if members.len() == 1 { continue }
+                let contours: Vec<&MFEKContour<MFEKPointData>> = members.iter().map(|&i| closed[i]).collect();
+                if let Some(united) = path_op(&closed_path(&contours), &Path::new(), PathOp::Union) {
+                    members.iter().for_each(|&i| placed[closed_idx[i]].clear());
+                    placed[closed_idx[members[0]]] = to_contours(&united);
+                }
+            }
+            layer.outline = placed.into_iter().flatten().collect();
+        });
+        self.end_layer_modification();
+
+        self.contour_idx = None;
+        self.point_idx = None;
+        self.selected.clear();
+    }
+}
@@ -0,0 +1,346 @@
+//! Glyph validation, for `--check` and the `:check` console command. Finds problems that don't stop
+//! a glyph from loading but make its outlines or exports wrong.
+
+use super::Editor;
+use super::booleans::{is_closed_outline, is_open};
+use super::groups;
+
+use derive_more::Display;
+use glifparser::Handle;
+use glifparser::glif::{ContourOperations, Layer, MFEKContour, MFEKPointData};
+
+use std::fmt;
+
+// Points closer than this, in font units, are considered to be at the same place.
+const EPSILON: f32 = 0.001;
+
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    #[display(fmt = "error")]
+    Error,
+    #[display(fmt = "warning")]
+    Warning,
+}
+
+/// Where a finding is. Anchors and the glyph as a whole have no layer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    pub layer: Option<usize>,
+    pub contour: Option<usize>,
+    pub point: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [("layer", self.layer), ("contour", self.contour), ("point", self.point)].iter()
+            .filter_map(|(name, idx)| idx.map(|idx| format!("{} {}", name, idx)))
+            .collect();
+        if parts.is_empty() {
+            write!(f, "glyph")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.severity, self.location, self.message)
+    }
+}
+
+fn at(layer: usize, contour: usize, point: Option<usize>) -> Location {
+    Location { layer: Some(layer), contour: Some(contour), point }
+}
+
+fn same_place(a: (f32, f32), b: (f32, f32)) -> bool {
+    (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON
+}
+
+/// The control polygon of a closed contour: on-curve points and their off-curve handles, in order.
+/// Good enough for telling direction and nesting apart.
+fn control_polygon(contour: &MFEKContour<MFEKPointData>) -> Vec<(f32, f32)> {
+    let n = contour.inner.len();
+    let mut ret = vec![];
+    for (i, p) in contour.inner.iter().enumerate() {
+        ret.push((p.x, p.y));
+        if let Handle::At(x, y) = p.a { ret.push((x, y)) }
+        if let Handle::At(x, y) = contour.inner[(i + 1) % n].b { ret.push((x, y)) }
+    }
+    ret
+}
+
+/// Positive when counter-clockwise, glif y being up.
+fn signed_area(polygon: &[(f32, f32)]) -> f32 {
+    let n = polygon.len();
+    (0..n).map(|i| {
+        let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+        p.0 * q.1 - q.0 * p.1
+    }).sum::<f32>() / 2.
+}
+
+fn polygon_contains(polygon: &[(f32, f32)], pt: (f32, f32)) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (p, q) = (polygon[i], polygon[(i + n - 1) % n]);
+        if (p.1 > pt.1) != (q.1 > pt.1) && pt.0 < (q.0 - p.0) * (pt.1 - p.1) / (q.1 - p.1) + p.0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn check_points(layer_idx: usize, contour_idx: usize, contour: &MFEKContour<MFEKPointData>, findings: &mut Vec<Finding>) {
+    let points = &contour.inner;
+    let n = points.len();
+    let segments = if is_open(contour) { n.saturating_sub(1) } else { n };
+
+    for i in 0..segments {
+        let (p, q) = (&points[i], &points[(i + 1) % n]);
+        if !same_place((p.x, p.y), (q.x, q.y)) { continue }
+        let handle_at_p = match p.a { Handle::At(x, y) => same_place((x, y), (p.x, p.y)), Handle::Colocated => true };
+        let handle_at_q = match q.b { Handle::At(x, y) => same_place((x, y), (q.x, q.y)), Handle::Colocated => true };
+        if handle_at_p && handle_at_q {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                location: at(layer_idx, contour_idx, Some(i)),
+                message: format!("Zero-length segment to point {}", (i + 1) % n),
+            });
+        }
+    }
+
+    for i in 0..n {
+        for j in i + 2..n {
+            // Adjacent points were handled above, including the closing segment.
+            if i == 0 && j == n - 1 && !is_open(contour) { continue }
+            if same_place((points[i].x, points[i].y), (points[j].x, points[j].y)) {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    location: at(layer_idx, contour_idx, Some(j)),
+                    message: format!("Overlaps point {}", i),
+                });
+            }
+        }
+    }
+}
+
+fn check_operation(layer_idx: usize, contour_idx: usize, contour: &MFEKContour<MFEKPointData>, findings: &mut Vec<Finding>) {
+    let n = contour.inner.len();
+    match &contour.operation {
+        Some(ContourOperations::VariableWidthStroke { data }) => {
+            // One handle per point, and one more for the end of a closed contour; the VWS tool
+            // always makes n+1.
+            if data.handles.len() < n {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    location: at(layer_idx, contour_idx, None),
+                    message: format!("Variable width stroke has {} handles for {} points; the last points have no width", data.handles.len(), n),
+                });
+            } else if data.handles.len() > n + 1 {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    location: at(layer_idx, contour_idx, None),
+                    message: format!("Variable width stroke has {} handles for {} points; the extra handles are ignored", data.handles.len(), n),
+                });
+            }
+        }
+        Some(ContourOperations::PatternAlongPath { data }) => {
+            if data.pattern.iter().all(|c| c.inner.is_empty()) {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    location: at(layer_idx, contour_idx, None),
+                    message: "Pattern along path has no pattern glyph".to_string(),
+                });
+            }
+        }
+        None => (),
+    }
+}
+
+// The findings about each contour of the layer at `layer_idx` on its own: whether the layer's
+// booleans leave it out, its operation, and its points.
+fn check_contours(layers: &[Layer<MFEKPointData>], layer_idx: usize, findings: &mut Vec<Finding>) {
+    let feeds_boolean = groups::feeds_boolean(layers, layer_idx);
+
+    for (contour_idx, contour) in layers[layer_idx].outline.iter().enumerate() {
+        if contour.inner.is_empty() { continue }
+
+        if feeds_boolean && !is_closed_outline(contour) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                location: at(layer_idx, contour_idx, None),
+                message: "Open contour in a layer used in a boolean operation; it will be left out".to_string(),
+            });
+        }
+
+        check_operation(layer_idx, contour_idx, contour, findings);
+        check_points(layer_idx, contour_idx, contour, findings);
+    }
+}
+
+impl Editor {
+    /// Inspects the loaded glyph and returns everything found wrong with it, errors first.
+    pub fn check(&self) -> Vec<Finding> {
+        let mut findings = vec![];
+
+        self.with_glyph(|glif| {
+            for (layer_idx, layer) in glif.layers.iter().enumerate() {
+                check_contours(&glif.layers, layer_idx, &mut findings);
+
+                // Direction: outer contours counter-clockwise, counters clockwise, as the UFO
+                // spec asks. Stroked and patterned contours are paths, not outlines, so skip them.
+                let polygons: Vec<(usize, Vec<(f32, f32)>)> = layer.outline.iter().enumerate()
+                    .filter(|(_, c)| c.inner.len() > 1 && !is_open(c) && c.operation.is_none())
+                    .map(|(i, c)| (i, control_polygon(c)))
+                    .collect();
+                for (contour_idx, polygon) in &polygons {
+                    let depth = polygons.iter()
+                        .filter(|(other_idx, other)| other_idx != contour_idx && polygon_contains(other, polygon[0]))
+                        .count();
+                    let ccw = signed_area(polygon) > 0.;
+                    if ccw != (depth % 2 == 0) {
+                        findings.push(Finding {
+                            severity: Severity::Warning,
+                            location: at(layer_idx, *contour_idx, None),
+                            message: format!("{} contour runs {}", if depth % 2 == 0 { "Outer" } else { "Inner" }, if ccw { "counter-clockwise" } else { "clockwise" }),
+                        });
+                    }
+                }
+
+                for (image, _matrix) in &layer.images {
+                    if !self.images.contains_key(&image.filename) {
+                        findings.push(Finding {
+                            severity: Severity::Error,
+                            location: Location { layer: Some(layer_idx), ..Location::default() },
+                            message: format!("Image {:?} is missing or unreadable", image.filename),
+                        });
+                    }
+                }
+            }
+
+            for (i, anchor) in glif.anchors.iter().enumerate() {
+                if anchor.class.trim().is_empty() {
+                    findings.push(Finding {
+                        severity: Severity::Warning,
+                        location: Location::default(),
+                        message: format!("Anchor {} at ({}, {}) has no name", i, anchor.x, anchor.y),
+                    });
+                }
+            }
+        });
+
+        findings.sort_by_key(|f| f.severity);
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use glifparser::{Contour, Point, PointType};
+    use glifparser::glif::{CapType, InterpolationType, JoinType, LayerOperation, PAPContour, PatternCopies, PatternSubdivide, VWSContour, VWSHandle};
+
+    use crate::editor::groups::GROUP_SEPARATOR;
+
+    fn contour(points: &[(f32, f32)]) -> MFEKContour<MFEKPointData> {
+        let contour: Contour<MFEKPointData> = points.iter().map(|&xy| Point::from_x_y_type(xy, PointType::Line)).collect();
+        contour.into()
+    }
+
+    fn open_contour(points: &[(f32, f32)]) -> MFEKContour<MFEKPointData> {
+        let mut contour = contour(points);
+        contour.inner[0].ptype = PointType::Move;
+        contour
+    }
+
+    fn layer(name: &str, outline: Vec<MFEKContour<MFEKPointData>>) -> Layer<MFEKPointData> {
+        Layer { name: name.to_string(), visible: true, color: None, outline, operation: None, images: vec![] }
+    }
+
+    fn findings(layers: &[Layer<MFEKPointData>], layer_idx: usize) -> Vec<(Severity, String)> {
+        let mut findings = vec![];
+        check_contours(layers, layer_idx, &mut findings);
+        findings.into_iter().map(|f| (f.severity, f.message)).collect()
+    }
+
+    fn open_contour_errors(layers: &[Layer<MFEKPointData>], layer_idx: usize) -> usize {
+        findings(layers, layer_idx).iter().filter(|(_, m)| m.starts_with("Open contour")).count()
+    }
+
+    const SQUARE: &[(f32, f32)] = &[(0., 0.), (0., 100.), (100., 100.), (100., 0.)];
+    const STROKE: &[(f32, f32)] = &[(0., 0.), (50., 50.), (100., 0.)];
+
+    #[test]
+    fn open_contours_only_matter_where_layers_are_combined() {
+        let mut layers = vec![layer("a", vec![open_contour(STROKE), contour(SQUARE)]), layer("b", vec![contour(SQUARE)])];
+        assert_eq!(open_contour_errors(&layers, 0), 0);
+
+        // The next layer folds into this one, so only its closed outline counts.
+        layers[1].operation = Some(LayerOperation::Union);
+        assert_eq!(open_contour_errors(&layers, 0), 1);
+        assert_eq!(open_contour_errors(&layers, 1), 0);
+
+        // Layers in a group are always combined.
+        let child = format!("g{}c", GROUP_SEPARATOR);
+        let layers = vec![layer("g", vec![]), layer(&child, vec![open_contour(STROKE)])];
+        assert_eq!(open_contour_errors(&layers, 1), 1);
+    }
+
+    #[test]
+    fn stroke_handle_counts() {
+        let vws = |handles: usize| {
+            let mut contour = open_contour(STROKE);
+            contour.operation = Some(ContourOperations::VariableWidthStroke { data: VWSContour {
+                handles: vec![VWSHandle { left_offset: 10., right_offset: 10., interpolation: InterpolationType::Linear, tangent_offset: 0. }; handles],
+                cap_start_type: CapType::Round,
+                cap_end_type: CapType::Round,
+                join_type: JoinType::Round,
+                remove_internal: false,
+                remove_external: false,
+            }});
+            let mut layers = vec![layer("a", vec![contour]), layer("b", vec![])];
+            layers[1].operation = Some(LayerOperation::Union);
+            findings(&layers, 0)
+        };
+
+        // A stroke closes its contour, so it isn't left out of the boolean.
+        assert!(vws(STROKE.len() + 1).is_empty());
+        assert!(vws(STROKE.len()).is_empty());
+        assert!(matches!(vws(STROKE.len() - 1).as_slice(), [(Severity::Error, _)]));
+        assert!(matches!(vws(STROKE.len() + 2).as_slice(), [(Severity::Warning, _)]));
+    }
+
+    #[test]
+    fn pattern_along_path_needs_a_pattern() {
+        let pap = |pattern: Vec<MFEKContour<MFEKPointData>>| {
+            let mut contour = open_contour(STROKE);
+            contour.operation = Some(ContourOperations::PatternAlongPath { data: PAPContour {
+                pattern,
+                copies: PatternCopies::Repeated,
+                subdivide: PatternSubdivide::Off,
+                is_vertical: false,
+                stretch: false,
+                spacing: 4.,
+                simplify: false,
+                normal_offset: 0.,
+                tangent_offset: 0.,
+                pattern_scale: (1., 1.),
+                center_pattern: true,
+            }});
+            findings(&[layer("a", vec![contour])], 0)
+        };
+
+        assert!(pap(vec![contour(SQUARE)]).is_empty());
+        assert_eq!(pap(vec![]), vec![(Severity::Error, "Pattern along path has no pattern glyph".to_string())]);
+        assert_eq!(pap(vec![contour(&[])]).len(), 1);
+    }
+}
@@ -3,61 +3,100 @@ use super::Editor;
 use glifparser::Glif;
 use glifparser::glif::{self, mfek::{MFEKGlif, MFEKPointData, Layer}};
 use log;
+use mfek_ipc::IPCInfo;
 use plist::{self, Value as PlistValue};
 
 use std::{fs, io, path};
 
 use crate::filedialog;
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
 
 impl Editor {
-    pub fn save_glif(&mut self, rename: bool) -> Result<path::PathBuf, ()> {
-        self.with_glyph(|glyph| {
-            let filename: path::PathBuf = if rename {
-                match filedialog::save_filename(Some("glif"), None) {
-                    Some(f) => f,
-                    None => return Err(())
-                }
-            } else {
-                glyph.filename.clone().unwrap()
-            };
+    /// Saves the glyph over its file. Returns the path written to.
+    pub fn save_glif(&mut self) -> Result<path::PathBuf, MFEKglifError> {
+        let filename = self.with_glyph(|glyph| glyph.filename.clone()).ok_or(MFEKglifError::NoFileChosen)?;
+        self.save_glif_as(&filename)?;
+        Ok(filename)
+    }
 
+    /// Saves the glyph to `filename`, which becomes its file. Its history stays as it is.
+    pub fn save_glif_as(&mut self, filename: &path::Path) -> Result<(), MFEKglifError> {
+        self.with_glyph(|glyph| {
             let glif_struct = glyph.clone().into();
-            glif::write_to_filename(&glif_struct, &filename).map(|()|log::info!("Requested save to {:?}", &filename)).unwrap_or_else(|e|panic!("Failed to write glif: {:?}", e));
-            Ok(filename)
-        })
+            write_glif(&glif_struct, filename)
+        })?;
+        log::info!("Requested save to {:?}", filename);
+        self.glyph.as_mut().unwrap().filename = Some(filename.to_path_buf());
+        self.history.mark_saved();
+
+        // The glyph is saved either way; a missing journal only costs the history.
+        if self.journal {
+            if let Err(e) = self.write_journal(filename) {
+                messages::emit(Message::Warning { message: e.to_string() });
+            }
+        }
+        Ok(())
     }
 
-    pub fn flatten_glif(&mut self, rename: bool) {
+    pub fn flatten_glif(&mut self, rename: bool) -> Result<(), MFEKglifError> {
         self.mark_preview_dirty();
         self.rebuild();
         let export = self.prepare_export();
         let layer = &export.layers[0];
         if export.layers.len() > 1 {
-            log::warn!("In a flatten operation, layers not in the topmost group will be discarded and not in your chosen file. You may want to export (Ctrl+E) and not flatten.");
+            messages::emit(Message::Warning { message: "In a flatten operation, layers not in the topmost group will be discarded and not in your chosen file. You may want to export (Ctrl+E) and not flatten.".to_string() });
         }
         
         let glif_struct = self.glyph.as_ref().unwrap().to_exported(&layer);
 
         self.with_glyph(|glyph| {
             let filename: std::path::PathBuf = if rename {
-                match filedialog::save_filename(Some("glif"), None) {
-                    Some(f) => f,
-                    None => return
-                }
+                filedialog::save_filename(Some("glif"), None).ok_or(MFEKglifError::NoFileChosen)?
             } else {
-                glyph.filename.clone().unwrap()
+                glyph.filename.clone().ok_or(MFEKglifError::NoFileChosen)?
             };
 
-            glif::write_to_filename(&glif_struct, &filename).map(|()|log::info!("Requested flatten to {:?}", &filename)).unwrap_or_else(|e|panic!("Failed to write glif: {:?}", e));
-        });
+            write_glif(&glif_struct, &filename)?;
+            log::info!("Requested flatten to {:?}", &filename);
+            Ok(())
+        })?;
+
+        // Flattening over the glyph's own file leaves it holding something no edit of ours matches.
+        if !rename {
+            self.history.mark_unsaved();
+        }
+        Ok(())
+    }
+
+    pub fn export_glif(&mut self) -> Result<(), MFEKglifError> {
+        let (export, font_pb) = self.export_glif_layers()?;
+
+        // layercontents.plist
+        if let Some(ref font) = font_pb {
+            let current_layercontents = read_layercontents_plist(font)?;
+            let our_layercontents = merge_layercontents_plist(&export.layers, current_layercontents);
+            let path = write_layercontents_plist(font, &our_layercontents)?;
+            messages::emit(Message::PlistMerged { path: &path });
+        }
+
+        Ok(())
     }
 
-    pub fn export_glif(&mut self) {
+    /// Writes every flattened layer group of the glyph, and the layerinfo.plist of each layer that
+    /// needs one, into the glyph's parent UFO. layercontents.plist is left to the caller, so that
+    /// callers exporting many glyphs can read and write it once. Returns the flattened glyph and the
+    /// UFO it was written into, if any.
+    pub fn export_glif_layers(&mut self) -> Result<(MFEKGlif<MFEKPointData>, Option<path::PathBuf>), MFEKglifError> {
         self.mark_preview_dirty();
         self.rebuild();
-        let glif_fn = self.with_glyph(|g|g.filename.as_ref().unwrap().file_name().unwrap().to_owned());
+        let glif_path = self.with_glyph(|g|g.filename.clone()).ok_or(MFEKglifError::NoFileChosen)?;
+        let glif_fn = glif_path.file_name().ok_or(MFEKglifError::NoFileChosen)?.to_owned();
         let glif_name = self.with_glyph(|g|g.name.clone());
-        let ipc_info = self.ipc_info.clone().expect("Cannot export w/o IPC data");
+        let ipc_info = match self.ipc_info.clone() {
+            Some(ipc_info) => ipc_info,
+            None => IPCInfo::from_glif_path("MFEKglif".to_string(), &glif_path),
+        };
 
         // `self.preview` contains flattened versions of all the layers, which are always cubic Bézier
         // splines. We know it's Some(_) because we rebuilt above.
@@ -65,14 +104,14 @@ impl Editor {
         // In the first phase, we iterate flattened layer groups ("previews") and write the glyph
         // data.
         let export = self.prepare_export();
+        messages::emit(Message::Rebuilt { glyph: &glif_name, layers: export.layers.len() });
 
         let font_pb = if let Some(ref font) = ipc_info.font {
             Some(font.clone())
         } else if export.layers.len() == 1 {
             None
         } else {
-            log::error!("Glyph has {} layers; font must have a parent UFO!", self.get_layer_count());
-            return
+            return Err(MFEKglifError::NoParentUfo(self.get_layer_count()));
         };
 
         for (i, layer) in export.layers.iter().enumerate() {
@@ -80,7 +119,7 @@ impl Editor {
 
             let target_dir = layer.to_glyphs_dir(i);
 
-            let mut target = self.glyph.as_ref().unwrap().filename.as_ref().unwrap().clone();
+            let mut target = glif_path.clone();
 
             match font_pb {
                 Some(ref pb) => {
@@ -89,26 +128,27 @@ impl Editor {
                     match fs::create_dir(&target) {
                         Err(e) => {
                             if e.kind() != io::ErrorKind::AlreadyExists {
-                                panic!("{:?}", e);
+                                return Err(MFEKglifError::CreateDir { path: target, error: e });
                             }
                         },
                         Ok(()) => ()
                     }
                     target.push(&glif_fn);
-                    log::info!("Targeting {:?} to write {}", &target, &layer.name);
+                    log::debug!("Targeting {:?} to write {}", &target, &layer.name);
                 },
                 None => ()
             }
 
             let glif_struct = self.glyph.as_ref().unwrap().to_exported(&layer);
-            glif::write_to_filename(&glif_struct, &target).unwrap_or_else(|e|panic!("Failed to write glif: {:?}", e));
+            write_glif(&glif_struct, &target)?;
+            messages::emit(Message::LayerExported { glyph: &glif_name, layer: i, name: &layer.name, path: &target });
 
             if font_pb.is_none() {
-                log::warn!("Exported .glif without a parent UFO font. Cannot create layer(info|contents).plist.");
+                messages::emit(Message::Warning { message: "Exported .glif without a parent UFO font. Cannot create layer(info|contents).plist.".to_string() });
                 if layer.color.is_some() {
-                    log::error!(".glif's layer 0 calls for a color, but it has no parent UFO. Cannot create layercontents.plist, color will be lost!")
+                    messages::emit(Message::Warning { message: ".glif's layer 0 calls for a color, but it has no parent UFO. Cannot create layercontents.plist, color will be lost!".to_string() });
                 }
-                return
+                return Ok((export, None))
             }
 
             // In the second phase, we write the plist files layerinfo.plist and
@@ -117,7 +157,6 @@ impl Editor {
             use glifparser::glif::mfek::layer::ToLayerInfoPlist;
             // layerinfo.plist
             let needs_layerinfo = layer.color.is_some();
-            if font_pb.is_none() { panic!("Cannot write layerinfo for glyph; not in a font!") }
             if needs_layerinfo {
                 let mut layerinfo = target.parent().expect("Cannot write layerinfo for glyph; at root of filesystem???").to_owned();
                 layerinfo.push("layerinfo.plist");
@@ -126,42 +165,61 @@ impl Editor {
                 let mut current_layerinfo_p = None;
                 if path::Path::exists(&layerinfo) {
                     log::info!("Layer already has layerinfo, checking compatibility");
-                    current_layerinfo_p = Some(PlistValue::from_file(&layerinfo).expect(&format!("Failed to deserialize layerinfo.plist in {:?}", &layerinfo)));
+                    current_layerinfo_p = Some(PlistValue::from_file(&layerinfo).map_err(|e| MFEKglifError::PlistRead { path: layerinfo.clone(), reason: e.to_string() })?);
                 }
 
                 let layerinfo_plist = layer.to_layerinfo_plist();
                 layerinfo_plist.map(|layerinfo_p_| {
                     current_layerinfo_p.map(|cmp| {
                         if layerinfo_p_ != cmp {
-                            log::warn!("I am replacing an existing layerinfo.plist with an incompatible layerinfo.plist. Other glyphs in this font may appear in different colors! This is not a bug in MFEKglif, you must use unique names for layers that will be differently colored *across* your font.");
+                            messages::emit(Message::Warning { message: format!("I am replacing an existing layerinfo.plist ({:?}) with an incompatible layerinfo.plist. Other glyphs in this font may appear in different colors! This is not a bug in MFEKglif, you must use unique names for layers that will be differently colored *across* your font.", &layerinfo) });
                         }
                     });
                     layerinfo_p = Some(layerinfo_p_);
                 });
 
                 if let Some(li) = layerinfo_p {
-                    li.to_file_xml(layerinfo).expect(&format!("Failed to write layerinfo.plist for layer {} in glyph {}", i, &glif_name));
-                    log::info!("Wrote layer {} of glyph {}'s layerinfo.plist. Color was {}", i, &glif_name, layer.color.unwrap().to_string());
+                    li.to_file_xml(&layerinfo).map_err(|e| MFEKglifError::PlistWrite { path: layerinfo.clone(), reason: e.to_string() })?;
+                    log::debug!("Layer {}'s color was {}", i, layer.color.unwrap().to_string());
+                    messages::emit(Message::LayerInfoWritten { glyph: &glif_name, layer: i, path: &layerinfo });
                 }
             }
         }
 
-        // layercontents.plist
-        use glifparser::glif::mfek::layer::ToLayerContentsPlist;
-        let mut our_layercontents = (&export.layers).as_slice().to_layercontents_plist();
-        let layercontents = font_pb.clone();
-        if let Some(mut layercontents_f) = layercontents {
-            layercontents_f.push("layercontents.plist");
-            if path::Path::exists(&layercontents_f) {
-                let current_layercontents_p = Some(PlistValue::from_file(&layercontents_f).expect(&format!("Failed to deserialize layercontents.plist in {:?}", &layercontents_f)));
-                our_layercontents = (&export.layers).as_slice().merge_layercontents_plists(current_layercontents_p.unwrap());
-            }
-            our_layercontents.to_file_xml(&layercontents_f).expect(&format!("Failed to write layercontents.plist for glyph {}", &glif_name));
-            log::info!("Wrote glyph {}'s layercontents.plist.", &glif_name);
-        }
+        Ok((export, font_pb))
     }
 }
 
+/// Reads the layercontents.plist of the UFO at `font`, if it has one.
+pub fn read_layercontents_plist(font: &path::Path) -> Result<Option<PlistValue>, MFEKglifError> {
+    let mut layercontents_f = font.to_path_buf();
+    layercontents_f.push("layercontents.plist");
+    if !path::Path::exists(&layercontents_f) { return Ok(None) }
+    PlistValue::from_file(&layercontents_f).map(Some).map_err(|e| MFEKglifError::PlistRead { path: layercontents_f.clone(), reason: e.to_string() })
+}
+
+/// Adds the layers of an exported glyph to `current`, or makes a new layercontents.plist for them if
+/// the UFO didn't have one yet.
+pub fn merge_layercontents_plist(layers: &[Layer<MFEKPointData>], current: Option<PlistValue>) -> PlistValue {
+    use glifparser::glif::mfek::layer::ToLayerContentsPlist;
+    match current {
+        Some(current) => layers.merge_layercontents_plists(current),
+        None => layers.to_layercontents_plist(),
+    }
+}
+
+/// Returns the path written to.
+pub fn write_layercontents_plist(font: &path::Path, layercontents: &PlistValue) -> Result<path::PathBuf, MFEKglifError> {
+    let mut layercontents_f = font.to_path_buf();
+    layercontents_f.push("layercontents.plist");
+    layercontents.to_file_xml(&layercontents_f).map_err(|e| MFEKglifError::PlistWrite { path: layercontents_f.clone(), reason: e.to_string() })?;
+    Ok(layercontents_f)
+}
+
+fn write_glif(glif_struct: &Glif<MFEKPointData>, filename: &path::Path) -> Result<(), MFEKglifError> {
+    glif::write_to_filename(glif_struct, filename).map_err(|e| MFEKglifError::GlifWrite { path: filename.to_path_buf(), reason: format!("{:?}", e) })
+}
+
 pub trait ExportLayer {
     fn to_exported(&self, layer: &Layer<MFEKPointData>) -> Glif<MFEKPointData>;
 }
@@ -0,0 +1,152 @@
+//! Editing several glyphs of one UFO in a single session. Only one glyph is ever loaded into the
+//! editor; the others that have been visited are parked here with their history and selection, so
+//! switching back to one is as if it had never been left.
+
+use super::Editor;
+use super::history::History;
+
+use glifparser::glif::{MFEKGlif, MFEKPointData};
+use plist::Value as PlistValue;
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::util::error::MFEKglifError;
+
+struct ParkedGlyph {
+    glyph: MFEKGlif<MFEKPointData>,
+    history: History,
+    layer_idx: Option<usize>,
+    contour_idx: Option<usize>,
+    point_idx: Option<usize>,
+    selected: HashSet<(usize, usize)>,
+}
+
+pub struct FontSession {
+    pub ufo: PathBuf,
+    /// Glyph names and .glif paths of the default layer, in contents.plist order.
+    pub glyphs: Vec<(String, PathBuf)>,
+    pub current: usize,
+    parked: HashMap<usize, ParkedGlyph>,
+}
+
+/// Reads the glyph names and .glif paths of a UFO's default layer from its contents.plist.
+pub fn read_contents_plist(ufo: &Path) -> Result<Vec<(String, PathBuf)>, MFEKglifError> {
+    let glyphs_dir = ufo.join("glyphs");
+    let contents_f = glyphs_dir.join("contents.plist");
+
+    let contents = PlistValue::from_file(&contents_f).map_err(|e| MFEKglifError::PlistRead { path: contents_f.clone(), reason: e.to_string() })?;
+    let contents = contents.as_dictionary().ok_or_else(|| MFEKglifError::PlistRead { path: contents_f.clone(), reason: "not a dictionary".to_string() })?;
+
+    let mut ret = vec![];
+    for (name, filename) in contents {
+        match filename.as_string() {
+            Some(f) => ret.push((name.clone(), glyphs_dir.join(f))),
+            None => log::warn!("Skipping glyph {} in {:?}, its file name is not a string", name, &contents_f),
+        }
+    }
+    Ok(ret)
+}
+
+impl FontSession {
+    pub fn open(ufo: &Path) -> Result<Self, MFEKglifError> {
+        let glyphs = read_contents_plist(ufo)?;
+        if glyphs.is_empty() {
+            return Err(MFEKglifError::EmptyUfo(ufo.to_path_buf()));
+        }
+        Ok(FontSession {
+            ufo: ufo.to_path_buf(),
+            glyphs,
+            current: 0,
+            parked: HashMap::new(),
+        })
+    }
+
+    /// If `glif` is in the default layer of a UFO, a session on that UFO with `glif` current.
+    pub fn for_glif(glif: &Path) -> Option<Self> {
+        let glyphs_dir = glif.parent()?;
+        if glyphs_dir.file_name()? != "glyphs" { return None }
+        let ufo = glyphs_dir.parent()?;
+        if !ufo.join("metainfo.plist").exists() { return None }
+
+        let mut session = FontSession::open(ufo).map_err(|e| log::warn!("Not opening the rest of the UFO: {}", e)).ok()?;
+        session.current = session.index_of(glif)?;
+        Some(session)
+    }
+
+    pub fn index_of(&self, glif: &Path) -> Option<usize> {
+        let glif = glif.canonicalize().ok()?;
+        self.glyphs.iter().position(|(_, p)| p.canonicalize().map(|p| p == glif).unwrap_or(false))
+    }
+
+    pub fn next(&self) -> usize {
+        (self.current + 1) % self.glyphs.len()
+    }
+
+    pub fn previous(&self) -> usize {
+        (self.current + self.glyphs.len() - 1) % self.glyphs.len()
+    }
+
+    /// Whether the glyph at `idx` is parked with unsaved changes.
+    pub fn is_edited(&self, idx: usize) -> bool {
+        self.parked.get(&idx).map(|p| !p.history.is_saved()).unwrap_or(false)
+    }
+
+    /// Whether the glyph at `idx` is parked, i.e. needn't be read from disk to switch to it.
+    pub fn is_parked(&self, idx: usize) -> bool {
+        self.parked.contains_key(&idx)
+    }
+
+    /// Makes the glyph at `idx` current in place of the current one, which was just saved over its
+    /// file. Whatever was parked for it no longer matches the file, so it is dropped.
+    pub fn replace_with_current(&mut self, idx: usize) {
+        self.parked.remove(&idx);
+        self.current = idx;
+    }
+
+    /// The names of the parked glyphs that are edited, in contents.plist order.
+    pub fn edited_glyphs(&self) -> Vec<&str> {
+        (0..self.glyphs.len()).filter(|&idx| self.is_edited(idx)).map(|idx| self.glyphs[idx].0.as_str()).collect()
+    }
+}
+
+impl Editor {
+    /// Moves the current glyph, its history and selection into the font session. Does nothing
+    /// without a session or glyph, or in the middle of a modification.
+    pub fn park_glyph(&mut self) {
+        if self.modifying || self.glyph.is_none() { return }
+        let current = match self.font.as_ref() {
+            Some(font) => font.current,
+            None => return,
+        };
+
+        let parked = ParkedGlyph {
+            glyph: self.glyph.take().unwrap(),
+            history: mem::replace(&mut self.history, History::new()),
+            layer_idx: self.layer_idx.take(),
+            contour_idx: self.contour_idx.take(),
+            point_idx: self.point_idx.take(),
+            selected: mem::take(&mut self.selected),
+        };
+        self.font.as_mut().unwrap().parked.insert(current, parked);
+    }
+
+    /// Brings a parked glyph back into the editor and makes it current. Returns false if the glyph
+    /// at `idx` was never parked, in which case it needs to be read from disk.
+    pub fn unpark_glyph(&mut self, idx: usize) -> bool {
+        let parked = match self.font.as_mut().and_then(|font| font.parked.remove(&idx)) {
+            Some(parked) => parked,
+            None => return false,
+        };
+
+        self.set_glyph(parked.glyph);
+        self.history = parked.history;
+        self.layer_idx = parked.layer_idx;
+        self.contour_idx = parked.contour_idx;
+        self.point_idx = parked.point_idx;
+        self.selected = parked.selected;
+        self.font.as_mut().unwrap().current = idx;
+        true
+    }
+}
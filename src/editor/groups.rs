@@ -0,0 +1,303 @@
+//! Layer groups. A layer's name says which groups it is in, as a path whose parts are separated by
+//! `GROUP_SEPARATOR`: `Shadow`, separator, `Inner` is the layer `Inner` in the group `Shadow`, and
+//! `Shadow` is the layer heading it. A group is a layer followed by layers one level deeper than it
+//! is. Its operation and visibility apply to the combined outline of its own contours and its
+//! layers, which are combined among themselves the way top-level layers always have been: each
+//! layer without an operation starts a combination, and the layers with one after it fold into it.
+//! Keeping this in the names means the .glif needs nothing new. The separator is a Unicode
+//! noncharacter, which no font source may contain, so files that predate groups never have one and
+//! export as before.
+
+use glifparser::glif::{Layer, LayerOperation, MFEKPointData};
+use glifparser::outline::skia::ToSkiaPaths;
+use skulpin::skia_safe::{Path, PathOp};
+
+use std::collections::HashSet;
+
+use super::Editor;
+
+pub const GROUP_SEPARATOR: char = '\u{FDD0}';
+
+pub fn path_op(op: &LayerOperation) -> PathOp {
+    match op {
+        LayerOperation::Difference => PathOp::Difference,
+        LayerOperation::Union => PathOp::Union,
+        LayerOperation::Intersect => PathOp::Intersect,
+        LayerOperation::XOR => PathOp::XOR,
+    }
+}
+
+/// How many groups the layer is in.
+pub fn depth(layer: &Layer<MFEKPointData>) -> usize {
+    layer.name.matches(GROUP_SEPARATOR).count()
+}
+
+/// The layer's own name, without the groups it is in.
+pub fn display_name(layer: &Layer<MFEKPointData>) -> &str {
+    layer.name.rsplit(GROUP_SEPARATOR).next().unwrap_or("")
+}
+
+// Edits the layer's name as the list of its parts.
+fn edit_path<F>(layer: &mut Layer<MFEKPointData>, f: F)
+    where F: FnOnce(&mut Vec<String>) {
+    let mut parts: Vec<String> = layer.name.split(GROUP_SEPARATOR).map(str::to_string).collect();
+    f(&mut parts);
+    layer.name = parts.join(&GROUP_SEPARATOR.to_string());
+}
+
+pub(super) fn set_part(layer: &mut Layer<MFEKPointData>, idx: usize, name: &str) {
+    edit_path(layer, |parts| parts[idx] = name.to_string());
+}
+
+fn insert_part(layer: &mut Layer<MFEKPointData>, idx: usize, name: &str) {
+    edit_path(layer, |parts| parts.insert(idx, name.to_string()));
+}
+
+fn remove_part(layer: &mut Layer<MFEKPointData>, idx: usize) {
+    edit_path(layer, |parts| { parts.remove(idx); });
+}
+
+/// Whether the layer at `idx` heads a group.
+pub fn is_group(layers: &[Layer<MFEKPointData>], idx: usize) -> bool {
+    layers.get(idx + 1).map(|next| depth(next) > depth(&layers[idx])).unwrap_or(false)
+}
+
+/// One past the last layer nested in the layer at `idx`, or `idx + 1` if it isn't a group.
+pub fn subtree_end(layers: &[Layer<MFEKPointData>], idx: usize) -> usize {
+    let d = depth(&layers[idx]);
+    let mut end = idx + 1;
+    while end < layers.len() && depth(&layers[end]) > d { end += 1 }
+    end
+}
+
+/// The group the layer at `idx` is in.
+pub fn parent(layers: &[Layer<MFEKPointData>], idx: usize) -> Option<usize> {
+    let d = depth(&layers[idx]);
+    (0..idx).rev().find(|&i| depth(&layers[i]) < d)
+}
+
+/// The layer or group before the layer at `idx` in the same group.
+pub fn previous_sibling(layers: &[Layer<MFEKPointData>], idx: usize) -> Option<usize> {
+    let d = depth(&layers[idx]);
+    (0..idx).rev().take_while(|&i| depth(&layers[i]) >= d).find(|&i| depth(&layers[i]) == d)
+}
+
+/// The layer or group after the layer at `idx` in the same group.
+pub fn next_sibling(layers: &[Layer<MFEKPointData>], idx: usize) -> Option<usize> {
+    let end = subtree_end(layers, idx);
+    if end < layers.len() && depth(&layers[end]) == depth(&layers[idx]) { Some(end) } else { None }
+}
+
+fn children(layers: &[Layer<MFEKPointData>], idx: usize) -> Vec<usize> {
+    let end = subtree_end(layers, idx);
+    let mut ret = vec![];
+    let mut child = idx + 1;
+    while child < end {
+        ret.push(child);
+        child = subtree_end(layers, child);
+    }
+    ret
+}
+
+/// Whether the layer at `idx` and every group it is in are visible.
+pub fn is_shown(layers: &[Layer<MFEKPointData>], idx: usize) -> bool {
+    layers[idx].visible && parent(layers, idx).map(|p| is_shown(layers, p)).unwrap_or(true)
+}
+
+/// Tells the group at `idx` apart from every other: its full path, and how many layers before it
+/// have the same one, since nothing stops two groups from having the same name.
+pub fn group_key(layers: &[Layer<MFEKPointData>], idx: usize) -> (String, usize) {
+    let name = &layers[idx].name;
+    (name.clone(), layers[..idx].iter().filter(|l| &l.name == name).count())
+}
+
+/// Whether the layer at `idx` is in the layer list, i.e. no group it is in is collapsed. `collapsed`
+/// holds the `group_key`s of the collapsed groups.
+pub fn is_listed(layers: &[Layer<MFEKPointData>], collapsed: &HashSet<(String, usize)>, idx: usize) -> bool {
+    match parent(layers, idx) {
+        Some(p) => !collapsed.contains(&group_key(layers, p)) && is_listed(layers, collapsed, p),
+        None => true,
+    }
+}
+
+/// Whether only the closed contours of the layer at `idx` make it into the glyph, as happens when it
+/// goes through a boolean operation: it has an operation, the layer or group after it folds into it,
+/// or it is in a group or heads one.
+pub fn feeds_boolean(layers: &[Layer<MFEKPointData>], idx: usize) -> bool {
+    layers[idx].operation.is_some()
+        || next_sibling(layers, idx).map(|next| layers[next].operation.is_some()).unwrap_or(false)
+        || parent(layers, idx).is_some()
+        || is_group(layers, idx)
+}
+
+fn combine(a: Path, b: &Path, op: PathOp) -> Path {
+    match a.op(b, op).and_then(|p| p.as_winding()) {
+        Some(result) => result,
+        None => a,
+    }
+}
+
+/// The closed outline the layer at `idx` brings to a boolean operation, None if it has none. For a
+/// group, the union of its own outline and the combinations of its visible layers.
+pub fn combined_path(layers: &[Layer<MFEKPointData>], idx: usize) -> Option<Path> {
+    let own = layers[idx].outline.to_skia_paths(None).closed;
+    if !is_group(layers, idx) {
+        return own;
+    }
+
+    let mut combinations: Option<Path> = own;
+    let mut current: Option<Path> = None;
+    for child in children(layers, idx) {
+        if !layers[child].visible { continue }
+        let path = match combined_path(layers, child) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        current = match (current, &layers[child].operation) {
+            (Some(current), Some(op)) => Some(combine(current, &path, path_op(op))),
+            (Some(current), None) => {
+                combinations = Some(match combinations {
+                    Some(combinations) => combine(combinations, &current, PathOp::Union),
+                    None => current,
+                });
+                Some(path)
+            }
+            (None, _) => Some(path),
+        };
+    }
+
+    match (combinations, current) {
+        (Some(combinations), Some(current)) => Some(combine(combinations, &current, PathOp::Union)),
+        (combinations, current) => combinations.or(current),
+    }
+}
+
+impl Editor {
+    /// Puts the layer at `idx` in a new group of its own. The group takes over the layer's operation,
+    /// so the glyph looks the same. Generates a history entry.
+    pub fn group_layer(&mut self, idx: usize) {
+        self.end_layer_modification();
+        self.begin_glyph_modification("Grouped layer.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (d, end) = (depth(&layers[idx]), subtree_end(layers, idx));
+        let mut header = Layer {
+            name: layers[idx].name.clone(),
+            visible: true,
+            color: None,
+            outline: vec![],
+            operation: layers[idx].operation.take(),
+            images: vec![],
+        };
+        set_part(&mut header, d, "Group");
+        layers[idx..end].iter_mut().for_each(|l| insert_part(l, d, "Group"));
+        layers.insert(idx, header);
+
+        self.end_glyph_modification();
+        self.set_active_layer(idx + 1);
+    }
+
+    /// Removes the group at `idx`, leaving its layers where they are. If the group has contours of its
+    /// own, it stays as a layer before them; if not, its first layer takes over its operation if it has
+    /// none of its own. Generates a history entry.
+    pub fn ungroup_layer(&mut self, idx: usize) {
+        if !self.with_glyph(|glif| is_group(&glif.layers, idx)) { return }
+
+        self.end_layer_modification();
+        self.begin_glyph_modification("Ungrouped layers.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (d, end) = (depth(&layers[idx]), subtree_end(layers, idx));
+        layers[idx + 1..end].iter_mut().for_each(|l| remove_part(l, d));
+        if layers[idx].outline.is_empty() {
+            let header = layers.remove(idx);
+            if layers[idx].operation.is_none() {
+                layers[idx].operation = header.operation;
+            }
+        }
+
+        self.end_glyph_modification();
+        let count = self.get_layer_count();
+        self.set_active_layer(idx.min(count - 1));
+    }
+
+    /// Moves the layer at `idx` to the end of the group right before it. Generates a history entry.
+    pub fn indent_layer(&mut self, idx: usize) {
+        let group = self.with_glyph(|glif| previous_sibling(&glif.layers, idx).filter(|&p| is_group(&glif.layers, p)));
+        let group = match group {
+            Some(group) => group,
+            None => return,
+        };
+
+        self.end_layer_modification();
+        self.begin_glyph_modification("Moved layer into group.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (d, end) = (depth(&layers[idx]), subtree_end(layers, idx));
+        let name = display_name(&layers[group]).to_string();
+        layers[idx..end].iter_mut().for_each(|l| insert_part(l, d, &name));
+
+        self.end_glyph_modification();
+    }
+
+    /// Moves the layer at `idx` out of its group, to right after the group. Generates a history
+    /// entry.
+    pub fn outdent_layer(&mut self, idx: usize) {
+        let group = match self.with_glyph(|glif| parent(&glif.layers, idx)) {
+            Some(group) => group,
+            None => return,
+        };
+
+        self.end_layer_modification();
+        self.begin_glyph_modification("Moved layer out of group.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (end, group_end) = (subtree_end(layers, idx), subtree_end(layers, group));
+        let d = depth(&layers[group]);
+        layers[idx..end].iter_mut().for_each(|l| remove_part(l, d));
+        layers[idx..group_end].rotate_left(end - idx);
+        let dest = group_end - (end - idx);
+
+        self.end_glyph_modification();
+        self.swap_active_layer(dest);
+    }
+
+    /// Swaps the layer at `idx`, and the layers in it if it's a group, with the layer or group before
+    /// or after it in the same group. Generates a history entry.
+    pub fn move_layer(&mut self, idx: usize, up: bool) {
+        let other = self.with_glyph(|glif| if up { previous_sibling(&glif.layers, idx) } else { next_sibling(&glif.layers, idx) });
+        let other = match other {
+            Some(other) => other,
+            None => return,
+        };
+
+        self.end_layer_modification();
+        self.begin_glyph_modification("Layer moved.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (first, second) = if up { (other, idx) } else { (idx, other) };
+        let (first_len, second_end) = (second - first, subtree_end(layers, second));
+        layers[first..second_end].rotate_left(first_len);
+        let dest = if up { other } else { second_end - first_len };
+
+        self.end_glyph_modification();
+        self.swap_active_layer(dest);
+    }
+
+    /// Renames the layer at `idx`, and the group it heads in the names of the layers in it. The group
+    /// separator would move the layer into another group, so it is left out. Generates a history
+    /// entry.
+    pub fn rename_layer(&mut self, idx: usize, name: &str) {
+        let name = name.replace(GROUP_SEPARATOR, "");
+
+        self.end_layer_modification();
+        self.begin_glyph_modification("Renamed layer.");
+
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        let (d, end) = (depth(&layers[idx]), subtree_end(layers, idx));
+        layers[idx..end].iter_mut().for_each(|l| set_part(l, d, &name));
+
+        self.end_glyph_modification();
+    }
+}
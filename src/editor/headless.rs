@@ -1,21 +1,63 @@
 use super::Editor;
+use super::batch;
+use super::check::Severity;
 
+use std::path::PathBuf;
 use std::process;
 
 use crate::io as glif_io;
+use crate::renderer::offscreen;
+use crate::scripting;
 use crate::util::argparser::{Args, HeadlessMode};
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
 
 impl Editor {
     pub fn headless(&mut self, args: &Args) -> ! {
+        messages::set_message_format(args.message_format);
+
+        if args.headless_mode == HeadlessMode::ExportBatch {
+            process::exit(batch::export_batch(args.filename.as_ref().unwrap()))
+        }
+
+        match self.run_headless(args) {
+            Ok(()) => process::exit(0),
+            Err(e) => {
+                messages::emit(Message::Error { error: &e });
+                process::exit(e.exit_code())
+            }
+        }
+    }
+
+    fn report_check(&self) -> Result<(), MFEKglifError> {
+        let findings = self.check();
+        for finding in &findings {
+            messages::emit(Message::Finding { finding });
+        }
+        let errors = findings.iter().filter(|f| f.severity == Severity::Error).count();
+        messages::emit(Message::CheckFinished { errors, warnings: findings.len() - errors });
+        if errors == 0 { Ok(()) } else { Err(MFEKglifError::CheckFailed(errors)) }
+    }
+
+    fn run_headless(&mut self, args: &Args) -> Result<(), MFEKglifError> {
         match args.filename {
-            Some(ref filename) => glif_io::load_glif_headless(self, &filename),
-            None => panic!("Cannot go headless without a .glif file to work on")
+            Some(ref filename) => glif_io::load_glif_headless(self, &filename)?,
+            None => return Err(MFEKglifError::NoFileChosen),
         }
         match args.headless_mode {
             HeadlessMode::None => panic!("Headless called on non-headless editor!"),
+            HeadlessMode::Export if args.watch => self.watch_and_export(),
             HeadlessMode::Export => self.export_glif(),
-            HeadlessMode::RunScript => unimplemented!()
+            HeadlessMode::ExportBatch => unreachable!(),
+            HeadlessMode::Check => self.report_check(),
+            HeadlessMode::Render => offscreen::render_to_file(self, args.render.as_ref().expect("Render mode requires render arguments")),
+            HeadlessMode::RunScript => {
+                let script = args.script.as_ref().expect("Script mode requires a script");
+                scripting::run_script(self, script).map_err(|e| MFEKglifError::Script {
+                    path: PathBuf::from(script),
+                    reason: e.to_string(),
+                })
+            }
         }
-        process::exit(0)
     }
 }
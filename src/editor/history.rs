@@ -1,111 +1,703 @@
-use glifparser::glif::{HistoryType, HistoryEntry, MFEKPointData};
+//! Undo and redo. While a modification is in progress, its entry holds a full copy of the layer or
+//! glyph as it was before; when the modification ends, the entry is compacted into a diff against
+//! the glyph after it, keeping only the contours that changed. Undoing a diff makes the diff that
+//! redoes it, so history stays small however big the glyph is. The oldest undo entries are dropped
+//! once the history's estimated size passes the limit set with `--history-limit`.
+
+use glifparser::MFEKGlif;
+use glifparser::Point;
+use glifparser::glif::{ContourOperations, HistoryType, HistoryEntry, Layer, MFEKContour, MFEKPointData, VWSHandle};
+
+use std::collections::HashSet;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::Editor;
 
+pub const DEFAULT_MEMORY_LIMIT_MB: usize = 256;
+static MEMORY_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_LIMIT_MB << 20);
+
+/// Sets how many bytes, by estimate, each glyph's history may hold.
+pub fn set_memory_limit(bytes: usize) {
+    MEMORY_LIMIT.store(bytes, Ordering::Relaxed);
+}
+
 pub struct History {
-    undo_stack: Vec<HistoryEntry<MFEKPointData>>,
-    pub redo_stack: Vec<HistoryEntry<MFEKPointData>>,
+    undo_stack: Vec<Entry>,
+    redo_stack: Vec<Entry>,
+    /// Estimated size of both stacks.
+    bytes: usize,
+    /// How many entries the undo stack had when the glyph was last loaded or saved, None once undo
+    /// and redo can no longer get back there.
+    saved_depth: Option<usize>,
+    pub snapshots: Vec<Snapshot>,
+    /// The two sides drawn over the glyph by the history panel's diff, before and after.
+    pub diff: (Option<DiffSide>, Option<DiffSide>),
+}
+
+/// A copy of the glyph's layers pinned under a name from the history panel. `depth` is how many
+/// entries the undo stack had when it was pinned; as long as those entries are still there, undoing
+/// or redoing to that depth gets back to the snapshot.
+pub struct Snapshot {
+    pub name: String,
+    pub layers: Vec<Layer<MFEKPointData>>,
+    pub depth: usize,
+    /// False once an edit made after undoing past the snapshot threw away the redo entries leading
+    /// back to it. It can still be diffed.
+    pub reachable: bool,
+}
+
+/// An undo or redo entry. A modification in progress keeps a copy of the layer it began on, as
+/// glifparser's `HistoryEntry`, or for edits to anything else (anchors, guidelines, width, or
+/// several layers at once) a copy of the whole glyph. Once it ends, that becomes a `Diff`. Journals
+/// from before diffs may also hold full copies.
+pub enum Entry {
+    Layer(HistoryEntry<MFEKPointData>),
+    Glyph(GlyphEntry),
+    Diff(DiffEntry),
+}
+
+pub struct GlyphEntry {
+    pub description: String,
+    pub layer_idx: Option<usize>,
+    pub contour_idx: Option<usize>,
+    pub point_idx: Option<usize>,
+    pub selected: Option<HashSet<(usize, usize)>>,
+    pub glyph: MFEKGlif<MFEKPointData>,
+}
+
+pub struct DiffEntry {
+    pub description: String,
+    pub layer_idx: Option<usize>,
+    pub contour_idx: Option<usize>,
+    pub point_idx: Option<usize>,
+    pub selected: Option<HashSet<(usize, usize)>>,
+    pub change: Change,
+}
+
+/// What a `DiffEntry` turns the glyph back into.
+pub enum Change {
+    Layer { idx: usize, diff: LayerDiff },
+    /// `rest` is the glyph without its layers: anchors, guidelines, width and so on.
+    Glyph { layers: Vec<LayerDiff>, rest: Box<MFEKGlif<MFEKPointData>> },
+}
+
+/// Turns one layer into another, holding only the contours that differ between them.
+pub struct LayerDiff {
+    /// The layer to turn into, without its outline: name, visibility, color, operation and images.
+    pub rest: Layer<MFEKPointData>,
+    /// The contours of the layer to turn into that the other layer doesn't have at the same index,
+    /// by index, in order.
+    pub contours: Vec<(usize, MFEKContour<MFEKPointData>)>,
+    /// How many contours the layer to turn into has.
+    pub len: usize,
+}
+
+impl LayerDiff {
+    /// The diff that turns `current`, or an empty layer if there's none, into `target`.
+    pub fn new(target: &Layer<MFEKPointData>, current: Option<&Layer<MFEKPointData>>) -> Self {
+        let contours = target.outline.iter().enumerate()
+            .filter(|(i, c)| current.and_then(|l| l.outline.get(*i)) != Some(*c))
+            .map(|(i, c)| (i, c.clone()))
+            .collect();
+        LayerDiff { rest: without_outline(target), contours, len: target.outline.len() }
+    }
+
+    pub fn apply(&self, current: Option<&Layer<MFEKPointData>>) -> Layer<MFEKPointData> {
+        let mut outline = current.map(|l| l.outline.clone()).unwrap_or_default();
+        outline.truncate(self.len);
+        for (i, contour) in &self.contours {
+            if *i < outline.len() {
+                outline[*i] = contour.clone();
+            } else {
+                outline.push(contour.clone());
+            }
+        }
+        let mut ret = self.rest.clone();
+        ret.outline = outline;
+        ret
+    }
+
+    fn size(&self) -> usize {
+        layer_size(&self.rest) + self.contours.iter().map(|(_, c)| contour_size(c)).sum::<usize>()
+    }
+}
+
+fn diff_layers(targets: &[Layer<MFEKPointData>], currents: &[Layer<MFEKPointData>]) -> Vec<LayerDiff> {
+    targets.iter().enumerate().map(|(i, target)| LayerDiff::new(target, currents.get(i))).collect()
+}
+
+fn without_outline(layer: &Layer<MFEKPointData>) -> Layer<MFEKPointData> {
+    Layer {
+        name: layer.name.clone(),
+        visible: layer.visible,
+        color: layer.color,
+        outline: vec![],
+        operation: layer.operation.clone(),
+        images: layer.images.clone(),
+    }
+}
+
+/// Splits a glyph into its layers and the rest of it.
+fn take_layers(mut glyph: MFEKGlif<MFEKPointData>) -> (Vec<Layer<MFEKPointData>>, MFEKGlif<MFEKPointData>) {
+    let layers = mem::take(&mut glyph.layers);
+    (layers, glyph)
+}
+
+// Sizes are estimates: what the structs take, plus their points and operation data. Strings and
+// images are small next to those.
+fn contour_size(contour: &MFEKContour<MFEKPointData>) -> usize {
+    let point_size = mem::size_of::<Point<MFEKPointData>>();
+    let operation = match &contour.operation {
+        Some(ContourOperations::VariableWidthStroke { data }) => data.handles.len() * mem::size_of::<VWSHandle>(),
+        Some(ContourOperations::PatternAlongPath { data }) => data.pattern.iter().map(|c| c.inner.len() * point_size).sum(),
+        _ => 0,
+    };
+    mem::size_of::<MFEKContour<MFEKPointData>>() + contour.inner.len() * point_size + operation
+}
+
+fn layer_size(layer: &Layer<MFEKPointData>) -> usize {
+    mem::size_of::<Layer<MFEKPointData>>() + layer.outline.iter().map(contour_size).sum::<usize>()
+}
+
+fn glyph_size(glyph: &MFEKGlif<MFEKPointData>) -> usize {
+    mem::size_of::<MFEKGlif<MFEKPointData>>() + glyph.layers.iter().map(layer_size).sum::<usize>()
+}
+
+impl Entry {
+    pub fn description(&self) -> &str {
+        match self {
+            Entry::Layer(entry) => &entry.description,
+            Entry::Glyph(entry) => &entry.description,
+            Entry::Diff(entry) => &entry.description,
+        }
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<Entry>() + match self {
+            Entry::Layer(entry) => layer_size(&entry.layer),
+            Entry::Glyph(entry) => glyph_size(&entry.glyph),
+            Entry::Diff(DiffEntry { change: Change::Layer { diff, .. }, .. }) => diff.size(),
+            Entry::Diff(DiffEntry { change: Change::Glyph { layers, rest }, .. }) => glyph_size(rest) + layers.iter().map(LayerDiff::size).sum::<usize>(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffSide {
+    Snapshot(usize),
+    Current,
 }
 
 impl History {
     pub fn new() -> Self {
         Self {
             undo_stack: vec!(),
-            redo_stack: vec!()
+            redo_stack: vec!(),
+            bytes: 0,
+            saved_depth: Some(0),
+            snapshots: vec!(),
+            diff: (None, None),
         }
     }
+
+    pub fn from_stacks(undo_stack: Vec<Entry>, redo_stack: Vec<Entry>) -> Self {
+        let bytes = undo_stack.iter().chain(redo_stack.iter()).map(Entry::size).sum();
+        let saved_depth = Some(undo_stack.len());
+        let mut ret = Self { undo_stack, redo_stack, bytes, saved_depth, ..Self::new() };
+        ret.trim();
+        ret
+    }
+
+    /// The undo and redo stacks, oldest entry first.
+    pub fn stacks(&self) -> (&[Entry], &[Entry]) {
+        (&self.undo_stack, &self.redo_stack)
+    }
 }
 
 impl History {
-    pub fn add_undo_entry(&mut self, entry: HistoryEntry<MFEKPointData> ) {
-        log::debug!("Added undo entry: {0}", entry.description);
-        self.undo_stack.push(entry);
+    pub fn add_undo_entry(&mut self, entry: Entry) {
+        log::debug!("Added undo entry: {0}", entry.description());
+        let depth = self.undo_stack.len();
+        for snapshot in self.snapshots.iter_mut().filter(|s| s.depth > depth) {
+            snapshot.reachable = false;
+        }
+        if self.saved_depth.map(|d| d > depth).unwrap_or(false) {
+            self.saved_depth = None;
+        }
+        self.bytes -= self.redo_stack.iter().map(Entry::size).sum::<usize>();
         self.redo_stack.clear();
+        self.push_undo(entry);
+    }
+
+    fn push_undo(&mut self, entry: Entry) {
+        self.bytes += entry.size();
+        self.undo_stack.push(entry);
+        self.trim();
+    }
+
+    fn pop_undo(&mut self) -> Option<Entry> {
+        let entry = self.undo_stack.pop()?;
+        self.bytes -= entry.size();
+        Some(entry)
+    }
+
+    fn push_redo(&mut self, entry: Entry) {
+        self.bytes += entry.size();
+        self.redo_stack.push(entry);
+    }
+
+    fn pop_redo(&mut self) -> Option<Entry> {
+        let entry = self.redo_stack.pop()?;
+        self.bytes -= entry.size();
+        Some(entry)
+    }
+
+    fn trim(&mut self) {
+        self.trim_to(MEMORY_LIMIT.load(Ordering::Relaxed));
+    }
+
+    /// Drops the oldest undo entries until history fits in `limit` bytes, always keeping the newest
+    /// one.
+    fn trim_to(&mut self, limit: usize) {
+        let mut dropped = 0;
+        while self.bytes > limit && self.undo_stack.len() > 1 {
+            let entry = self.undo_stack.remove(0);
+            self.bytes -= entry.size();
+            for snapshot in self.snapshots.iter_mut() {
+                if snapshot.depth == 0 {
+                    snapshot.reachable = false;
+                } else {
+                    snapshot.depth -= 1;
+                }
+            }
+            self.saved_depth = self.saved_depth.and_then(|d| d.checked_sub(1));
+            dropped += 1;
+        }
+        if dropped > 0 {
+            log::debug!("History over {} bytes, dropped the {} oldest undo entries", limit, dropped);
+        }
+    }
+
+    /// Estimated bytes held by the undo and redo stacks.
+    pub fn size(&self) -> usize {
+        self.bytes
+    }
+
+    /// How many entries can be undone, which is also where in history the glyph is.
+    pub fn depth(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Records that the glyph as it is now is what its file holds.
+    pub fn mark_saved(&mut self) {
+        self.saved_depth = Some(self.depth());
+    }
+
+    /// Records that the glyph's file holds something no point in history matches.
+    pub fn mark_unsaved(&mut self) {
+        self.saved_depth = None;
+    }
+
+    /// Whether the glyph is as it was last loaded or saved, i.e. has no unsaved changes.
+    pub fn is_saved(&self) -> bool {
+        self.saved_depth == Some(self.depth())
     }
 }
 
 impl Editor {
-    /// Pops a HistoryEntry off the layer stack and restores it.
-    pub fn undo(&mut self) {
+    /// Forgets all undo and redo entries, for when a different glyph is loaded.
+    pub fn reset_history(&mut self) {
+        self.history = History::new();
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Undoes or redoes until the undo stack holds `depth` entries, or there is nothing left to redo.
+    pub fn jump_to_history(&mut self, depth: usize) {
         if self.modifying { return; }
-        let entry = self.history.undo_stack.pop();
-        
-        if let Some(undo_entry) = entry {
-            self.history.redo_stack.push(HistoryEntry {
-                description: "Undo".to_owned(),
-                layer_idx: self.layer_idx,
-                contour_idx: self.contour_idx,
-                point_idx: self.point_idx,
-                selected: Some(self.selected.clone()),
-                layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
-                kind: undo_entry.kind.clone()
-            });
-    
-            match undo_entry.kind {
-                HistoryType::LayerModified => {
-                    self.glyph.as_mut().unwrap().layers[undo_entry.layer_idx.unwrap()] = undo_entry.layer;
-                }
-                HistoryType::LayerAdded => {
-                    self.glyph.as_mut().unwrap().layers.pop();
-                }
-                HistoryType::LayerDeleted => {
-                    self.glyph.as_mut().unwrap().layers.insert(undo_entry.layer_idx.unwrap(), undo_entry.layer);
+        while self.history.undo_stack.len() > depth {
+            self.undo();
+        }
+        while self.history.undo_stack.len() < depth && !self.history.redo_stack.is_empty() {
+            self.redo();
+        }
+    }
+
+    pub fn pin_snapshot(&mut self, name: String) {
+        let layers = self.with_glyph(|glif| glif.layers.clone());
+        self.history.snapshots.push(Snapshot { name, layers, depth: self.history.depth(), reachable: true });
+    }
+
+    /// Goes back (or forward) to a pinned snapshot. Returns false if history no longer leads to it.
+    pub fn restore_snapshot(&mut self, idx: usize) -> bool {
+        match self.history.snapshots.get(idx) {
+            Some(snapshot) if snapshot.reachable => {
+                let depth = snapshot.depth;
+                self.jump_to_history(depth);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn delete_snapshot(&mut self, idx: usize) {
+        self.history.snapshots.remove(idx);
+        // Keep the diff pointing at the same snapshots.
+        let fix = |side: Option<DiffSide>| match side {
+            Some(DiffSide::Snapshot(i)) if i == idx => None,
+            Some(DiffSide::Snapshot(i)) if i > idx => Some(DiffSide::Snapshot(i - 1)),
+            side => side,
+        };
+        self.history.diff = (fix(self.history.diff.0), fix(self.history.diff.1));
+        self.mark_preview_dirty();
+    }
+
+    pub fn set_history_diff(&mut self, before: Option<DiffSide>, after: Option<DiffSide>) {
+        self.history.diff = (before, after);
+        self.mark_preview_dirty();
+    }
+
+    /// The layers on either side of the history diff, if both sides are chosen.
+    pub fn history_diff_layers(&self) -> Option<(Vec<Layer<MFEKPointData>>, Vec<Layer<MFEKPointData>>)> {
+        let side = |side: Option<DiffSide>| match side? {
+            DiffSide::Snapshot(i) => self.history.snapshots.get(i).map(|s| s.layers.clone()),
+            DiffSide::Current => Some(self.with_glyph(|glif| glif.layers.clone())),
+        };
+        Some((side(self.history.diff.0)?, side(self.history.diff.1)?))
+    }
+
+    /// A glyph entry that restores the glyph, selection and active layer as they are now.
+    pub(super) fn glyph_entry(&self, description: &str) -> GlyphEntry {
+        GlyphEntry {
+            description: description.to_owned(),
+            layer_idx: self.layer_idx,
+            contour_idx: self.contour_idx,
+            point_idx: self.point_idx,
+            selected: Some(self.selected.clone()),
+            glyph: self.glyph.as_ref().unwrap().clone(),
+        }
+    }
+
+    /// Turns the newest undo entry, a full copy made when a modification began, into a diff against
+    /// the glyph as the modification left it.
+    pub(super) fn compact_history(&mut self) {
+        let glyph = self.glyph.as_ref().unwrap();
+        let compacted = match self.history.pop_undo() {
+            Some(Entry::Layer(entry)) if matches!(entry.kind, HistoryType::LayerModified) => {
+                let idx = entry.layer_idx.unwrap();
+                DiffEntry {
+                    change: Change::Layer { idx, diff: LayerDiff::new(&entry.layer, glyph.layers.get(idx)) },
+                    description: entry.description,
+                    layer_idx: entry.layer_idx,
+                    contour_idx: entry.contour_idx,
+                    point_idx: entry.point_idx,
+                    selected: entry.selected,
                 }
-                HistoryType::LayerMoved { to, from } => {
-                    self.swap_layers(to, from, false)
+            }
+            Some(Entry::Glyph(entry)) => {
+                let (layers, rest) = take_layers(entry.glyph);
+                DiffEntry {
+                    change: Change::Glyph { layers: diff_layers(&layers, &glyph.layers), rest: Box::new(rest) },
+                    description: entry.description,
+                    layer_idx: entry.layer_idx,
+                    contour_idx: entry.contour_idx,
+                    point_idx: entry.point_idx,
+                    selected: entry.selected,
                 }
             }
+            Some(entry) => {
+                self.history.push_undo(entry);
+                return
+            }
+            None => return,
+        };
+        self.history.push_undo(Entry::Diff(compacted));
+    }
 
-            self.layer_idx = undo_entry.layer_idx;
-            self.contour_idx = undo_entry.contour_idx;
-            self.point_idx = undo_entry.point_idx;
-            if let Some(selected) = undo_entry.selected {
-                self.selected = selected
+    /// Applies a diff entry and returns the one that takes the glyph back to how it is now.
+    fn apply_diff_entry(&mut self, entry: DiffEntry) -> DiffEntry {
+        let glyph = self.glyph.as_mut().unwrap();
+        let change = match entry.change {
+            Change::Layer { idx, diff } => {
+                let restored = diff.apply(glyph.layers.get(idx));
+                let inverse = LayerDiff::new(&glyph.layers[idx], Some(&restored));
+                glyph.layers[idx] = restored;
+                Change::Layer { idx, diff: inverse }
+            }
+            Change::Glyph { layers, rest } => {
+                let mut restored = *rest;
+                restored.layers = layers.iter().enumerate().map(|(i, diff)| diff.apply(glyph.layers.get(i))).collect();
+                // Saving as another file is not an edit; keep the name the glyph has now.
+                restored.filename = glyph.filename.clone();
+                let (current_layers, current_rest) = take_layers(mem::replace(glyph, restored));
+                Change::Glyph { layers: diff_layers(&current_layers, &glyph.layers), rest: Box::new(current_rest) }
             }
+        };
+
+        let inverse = DiffEntry {
+            description: entry.description,
+            layer_idx: self.layer_idx,
+            contour_idx: self.contour_idx,
+            point_idx: self.point_idx,
+            selected: Some(self.selected.clone()),
+            change,
+        };
 
-            self.mark_preview_dirty();
+        self.layer_idx = entry.layer_idx;
+        self.contour_idx = entry.contour_idx;
+        self.point_idx = entry.point_idx;
+        if let Some(selected) = entry.selected {
+            self.selected = selected
         }
+
+        self.mark_preview_dirty();
+        inverse
     }
 
-    pub fn redo(&mut self) {
+    fn restore_glyph_entry(&mut self, entry: GlyphEntry) {
+        let mut glyph = entry.glyph;
+        // Saving as another file is not an edit; keep the name the glyph has now.
+        glyph.filename = self.glyph.as_ref().unwrap().filename.clone();
+        self.glyph = Some(glyph);
+
+        self.layer_idx = entry.layer_idx;
+        self.contour_idx = entry.contour_idx;
+        self.point_idx = entry.point_idx;
+        if let Some(selected) = entry.selected {
+            self.selected = selected
+        }
+
+        self.mark_preview_dirty();
+    }
+
+    /// Pops an entry off the undo stack and restores it.
+    pub fn undo(&mut self) {
         if self.modifying { return; }
-        let entry = self.history.redo_stack.pop();
+        let entry = self.history.pop_undo();
+        
+        match entry {
+            Some(Entry::Layer(undo_entry)) => {
+                // Keeps the description, so the history panel can say what redoing will do.
+                self.history.push_redo(Entry::Layer(HistoryEntry {
+                    description: undo_entry.description.clone(),
+                    layer_idx: self.layer_idx,
+                    contour_idx: self.contour_idx,
+                    point_idx: self.point_idx,
+                    selected: Some(self.selected.clone()),
+                    layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
+                    kind: undo_entry.kind.clone()
+                }));
         
-        if let Some(redo_entry) = entry {
-            self.history.undo_stack.push(HistoryEntry {
-                description: "Redo".to_owned(),
-                layer_idx: self.layer_idx,
-                contour_idx: self.contour_idx,
-                point_idx: self.point_idx,
-                selected: Some(self.selected.clone()),
-                layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
-                kind: redo_entry.kind.clone()
-            });
-    
-
-            match redo_entry.kind {
-                HistoryType::LayerModified => {
-                    self.glyph.as_mut().unwrap().layers[redo_entry.layer_idx.unwrap()] = redo_entry.layer;
+                match undo_entry.kind {
+                    HistoryType::LayerModified => {
+                        self.glyph.as_mut().unwrap().layers[undo_entry.layer_idx.unwrap()] = undo_entry.layer;
+                    }
+                    HistoryType::LayerAdded => {
+                        self.glyph.as_mut().unwrap().layers.pop();
+                    }
+                    HistoryType::LayerDeleted => {
+                        self.glyph.as_mut().unwrap().layers.insert(undo_entry.layer_idx.unwrap(), undo_entry.layer);
+                    }
+                    HistoryType::LayerMoved { to, from } => {
+                        self.swap_layers(to, from, false)
+                    }
                 }
-                HistoryType::LayerAdded => {
-                    self.glyph.as_mut().unwrap().layers.push(redo_entry.layer);
+
+                self.layer_idx = undo_entry.layer_idx;
+                self.contour_idx = undo_entry.contour_idx;
+                self.point_idx = undo_entry.point_idx;
+                if let Some(selected) = undo_entry.selected {
+                    self.selected = selected
                 }
-                HistoryType::LayerDeleted => {
-                    self.glyph.as_mut().unwrap().layers.insert(redo_entry.layer_idx.unwrap(), redo_entry.layer);
+
+                self.mark_preview_dirty();
+            }
+            Some(Entry::Glyph(undo_entry)) => {
+                let redo_entry = self.glyph_entry(&undo_entry.description);
+                self.history.push_redo(Entry::Glyph(redo_entry));
+                self.restore_glyph_entry(undo_entry);
+            }
+            Some(Entry::Diff(undo_entry)) => {
+                let redo_entry = self.apply_diff_entry(undo_entry);
+                self.history.push_redo(Entry::Diff(redo_entry));
+            }
+            None => (),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.modifying { return; }
+        let entry = self.history.pop_redo();
+        
+        match entry {
+            Some(Entry::Layer(redo_entry)) => {
+                self.history.push_undo(Entry::Layer(HistoryEntry {
+                    description: redo_entry.description.clone(),
+                    layer_idx: self.layer_idx,
+                    contour_idx: self.contour_idx,
+                    point_idx: self.point_idx,
+                    selected: Some(self.selected.clone()),
+                    layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
+                    kind: redo_entry.kind.clone()
+                }));
+
+                match redo_entry.kind {
+                    HistoryType::LayerModified => {
+                        self.glyph.as_mut().unwrap().layers[redo_entry.layer_idx.unwrap()] = redo_entry.layer;
+                    }
+                    HistoryType::LayerAdded => {
+                        self.glyph.as_mut().unwrap().layers.push(redo_entry.layer);
+                    }
+                    HistoryType::LayerDeleted => {
+                        self.glyph.as_mut().unwrap().layers.insert(redo_entry.layer_idx.unwrap(), redo_entry.layer);
+                    }
+                    HistoryType::LayerMoved { to, from } => {
+                        self.swap_layers(from, to, false)
+                    }
                 }
-                HistoryType::LayerMoved { to, from } => {
-                    self.swap_layers(from, to, false)
+
+                self.layer_idx = redo_entry.layer_idx;
+                self.contour_idx = redo_entry.contour_idx;
+                self.point_idx = redo_entry.point_idx;
+                if let Some(selected) = redo_entry.selected {
+                    self.selected = selected
                 }
-            }
 
-            self.layer_idx = redo_entry.layer_idx;
-            self.contour_idx = redo_entry.contour_idx;
-            self.point_idx = redo_entry.point_idx;
-            if let Some(selected) = redo_entry.selected {
-                self.selected = selected
+                self.mark_preview_dirty();
             }
+            Some(Entry::Glyph(redo_entry)) => {
+                let undo_entry = self.glyph_entry(&redo_entry.description);
+                self.history.push_undo(Entry::Glyph(undo_entry));
+                self.restore_glyph_entry(redo_entry);
+            }
+            Some(Entry::Diff(redo_entry)) => {
+                let undo_entry = self.apply_diff_entry(redo_entry);
+                self.history.push_undo(Entry::Diff(undo_entry));
+            }
+            None => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use glifparser::{Contour, PointType};
+
+    fn contour(points: &[(f32, f32)]) -> MFEKContour<MFEKPointData> {
+        let contour: Contour<MFEKPointData> = points.iter().map(|&xy| Point::from_x_y_type(xy, PointType::Line)).collect();
+        contour.into()
+    }
+
+    fn layer(name: &str, outline: Vec<MFEKContour<MFEKPointData>>) -> Layer<MFEKPointData> {
+        Layer { name: name.to_string(), visible: true, color: None, outline, operation: None, images: vec![] }
+    }
+
+    fn diff_entry(description: &str, diff: LayerDiff) -> Entry {
+        Entry::Diff(DiffEntry {
+            description: description.to_string(),
+            layer_idx: Some(0),
+            contour_idx: None,
+            point_idx: None,
+            selected: None,
+            change: Change::Layer { idx: 0, diff },
+        })
+    }
+
+    fn assert_same(a: &Layer<MFEKPointData>, b: &Layer<MFEKPointData>) {
+        assert_eq!((&a.name, a.visible), (&b.name, b.visible));
+        assert!(a.outline == b.outline);
+    }
+
+    fn round_trip(target: &Layer<MFEKPointData>, current: Option<&Layer<MFEKPointData>>) {
+        let diff = LayerDiff::new(target, current);
+        assert_same(&diff.apply(current), target);
+    }
+
+    #[test]
+    fn layer_diff_keeps_only_changed_contours() {
+        let current = layer("a", vec![contour(&[(0., 0.), (1., 1.)]), contour(&[(2., 2.), (3., 3.)])]);
+        let target = layer("a", vec![contour(&[(0., 0.), (1., 1.)]), contour(&[(5., 5.), (3., 3.)])]);
+        let diff = LayerDiff::new(&target, Some(&current));
+        assert_eq!(diff.contours.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![1]);
+        assert_same(&diff.apply(Some(&current)), &target);
+    }
+
+    #[test]
+    fn layer_diff_round_trips() {
+        let one = contour(&[(0., 0.), (10., 0.), (10., 10.)]);
+        let two = contour(&[(20., 20.), (30., 20.)]);
+        let three = contour(&[(-5., -5.), (-5., 5.)]);
+
+        let base = layer("a", vec![one.clone(), two.clone()]);
+        // Contours removed, added, changed, and the rest of the layer changed.
+        round_trip(&layer("a", vec![one.clone()]), Some(&base));
+        round_trip(&layer("a", vec![one.clone(), two.clone(), three.clone()]), Some(&base));
+        round_trip(&layer("a", vec![three.clone(), two.clone()]), Some(&base));
+        round_trip(&layer("b", vec![]), Some(&base));
+        // From no layer at all, and back to the base from each.
+        round_trip(&base, None);
+        round_trip(&base, Some(&layer("a", vec![three.clone()])));
+        round_trip(&base, Some(&layer("a", vec![two, one, three])));
+    }
+
+    #[test]
+    fn trim_drops_oldest_and_keeps_snapshot_depths() {
+        let mut history = History::new();
+        for i in 0..4 {
+            let diff = LayerDiff::new(&layer("a", vec![contour(&[(i as f32, 0.), (0., 0.)])]), None);
+            history.undo_stack.push(diff_entry(&format!("{}", i), diff));
+        }
+        history.bytes = history.undo_stack.iter().map(Entry::size).sum();
+        let entry_size = history.undo_stack[0].size();
+        for (name, depth) in &[("start", 0), ("first", 1), ("end", 4)] {
+            history.snapshots.push(Snapshot { name: name.to_string(), layers: vec![], depth: *depth, reachable: true });
+        }
+
+        // Room for two entries: the two oldest go.
+        history.trim_to(entry_size * 2);
+        assert_eq!(history.undo_stack.iter().map(Entry::description).collect::<Vec<_>>(), vec!["2", "3"]);
+        assert_eq!(history.size(), history.undo_stack.iter().map(Entry::size).sum::<usize>());
+        let depths: Vec<_> = history.snapshots.iter().map(|s| (s.depth, s.reachable)).collect();
+        assert_eq!(depths, vec![(0, false), (0, false), (2, true)]);
+
+        // However small the limit, the newest entry stays.
+        history.trim_to(0);
+        assert_eq!(history.undo_stack.iter().map(Entry::description).collect::<Vec<_>>(), vec!["3"]);
+        assert_eq!(history.size(), entry_size);
+        assert_eq!(history.snapshots[2].depth, 1);
+        assert!(history.snapshots[2].reachable);
+    }
+
+    #[test]
+    fn saved_depth_survives_undo_and_redo_but_not_a_new_branch() {
+        let entry = |i: usize| diff_entry(&format!("{}", i), LayerDiff::new(&layer("a", vec![contour(&[(i as f32, 0.), (0., 0.)])]), None));
+        let mut history = History::new();
+        assert!(history.is_saved());
+        history.add_undo_entry(entry(0));
+        assert!(!history.is_saved());
+        history.mark_saved();
+        history.add_undo_entry(entry(1));
+        assert!(!history.is_saved());
+
+        // Undoing back to the save is saved again, and redoing past it isn't.
+        let undone = history.pop_undo().unwrap();
+        assert!(history.is_saved());
+        history.push_redo(undone);
+        let redone = history.pop_redo().unwrap();
+        history.push_undo(redone);
+        assert!(!history.is_saved());
 
-            self.mark_preview_dirty();
-        }    
+        // An edit made after undoing past the save can't get back to it.
+        history.pop_undo();
+        history.pop_undo();
+        history.add_undo_entry(entry(2));
+        assert!(!history.is_saved());
+        history.pop_undo();
+        assert!(!history.is_saved());
     }
 }
@@ -0,0 +1,319 @@
+//! The undo journal: an opt-in (`--history-journal`) copy of the undo and redo stacks kept on disk,
+//! so that history survives a restart. It is written whenever the glyph is saved and read back when
+//! the glyph is loaded, but only if the .glif is still the one that was saved with it.
+//!
+//! For a glyph in a UFO the journal lives in the UFO's `data/` directory, which the UFO spec sets
+//! aside for application data; otherwise it is a hidden file next to the .glif. It is a plist whose
+//! entries hold their layer as a one-layer MFEK .glif, or for glyph entries the whole glyph, written
+//! and read by glifparser like any other. Diff entries are written the same way, with only their
+//! changed contours in the outlines and the indices those contours go at alongside.
+
+use super::Editor;
+use super::history::{Change, DiffEntry, Entry, GlyphEntry, History, LayerDiff};
+
+use glifparser::Glif;
+use glifparser::glif::{self, HistoryEntry, HistoryType, Layer, MFEKGlif, MFEKPointData};
+use mfek_ipc::IPCInfo;
+use plist::{Dictionary, Value as PlistValue};
+
+use std::collections::HashSet;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::util::error::MFEKglifError;
+
+/// Version 2 added diff entries. Version 1 journals are still read.
+const JOURNAL_VERSION: u64 = 2;
+/// Entries kept per stack. Older undo entries are dropped first.
+const MAX_JOURNAL_ENTRIES: usize = 256;
+
+fn journal_path(glif_path: &Path) -> PathBuf {
+    let glif_fn = glif_path.file_name().unwrap().to_string_lossy().into_owned();
+    match IPCInfo::from_glif_path("MFEKglif".to_string(), &glif_path.to_path_buf()).font {
+        Some(ufo) => ufo.join("data").join("org.MFEK.MFEKglif").join("history").join(format!("{}.plist", glif_fn)),
+        None => glif_path.with_file_name(format!(".{}.history.plist", glif_fn)),
+    }
+}
+
+// 64-bit FNV-1a. Unlike std's hashers it is fixed, so a journal matches its .glif whichever build
+// of MFEKglif wrote it.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn glif_hash(glif_path: &Path) -> Option<String> {
+    let bytes = fs::read(glif_path).ok()?;
+    Some(format!("{:016x}", fnv1a(&bytes)))
+}
+
+fn opt_index(dict: &Dictionary, key: &str) -> Option<usize> {
+    dict.get(key).and_then(PlistValue::as_unsigned_integer).map(|i| i as usize)
+}
+
+pub(super) fn write_glif_xml(glif: MFEKGlif<MFEKPointData>) -> Result<String, String> {
+    let glif: Glif<MFEKPointData> = glif.into();
+    glif::write(&glif).map_err(|e| format!("{:?}", e))
+}
+
+// A glif with only `layer` in it, for an entry to be written as.
+fn one_layer_glif(name: &str, layer: Layer<MFEKPointData>) -> MFEKGlif<MFEKPointData> {
+    let mut glif: MFEKGlif<MFEKPointData> = Glif::new().into();
+    glif.name = name.to_string();
+    glif.layers = vec![layer];
+    glif
+}
+
+pub(super) fn read_glif_xml(xml: &str) -> Option<MFEKGlif<MFEKPointData>> {
+    let glif: Glif<MFEKPointData> = glif::read(xml).ok()?;
+    Some(glif.into())
+}
+
+/// The fields layer and glyph entries share.
+fn common_to_plist(description: &str, indices: [(&str, Option<usize>); 3], selected: &Option<HashSet<(usize, usize)>>) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.insert("description".to_string(), description.into());
+    for (key, idx) in &indices {
+        if let Some(idx) = idx {
+            dict.insert(key.to_string(), (*idx as u64).into());
+        }
+    }
+    if let Some(ref selected) = selected {
+        let selected: Vec<PlistValue> = selected.iter()
+            .map(|(c, p)| PlistValue::Array(vec![(*c as u64).into(), (*p as u64).into()]))
+            .collect();
+        dict.insert("selected".to_string(), PlistValue::Array(selected));
+    }
+    dict
+}
+
+fn selected_from_plist(dict: &Dictionary) -> Option<HashSet<(usize, usize)>> {
+    dict.get("selected").and_then(PlistValue::as_array).map(|sel| {
+        sel.iter().filter_map(|cp| {
+            let cp = cp.as_array()?;
+            Some((cp.get(0)?.as_unsigned_integer()? as usize, cp.get(1)?.as_unsigned_integer()? as usize))
+        }).collect()
+    })
+}
+
+/// A diff's changed contours as a layer, and the indices they go at.
+fn layer_diff_to_layer(diff: &LayerDiff) -> (Layer<MFEKPointData>, PlistValue) {
+    let mut layer = diff.rest.clone();
+    layer.outline = diff.contours.iter().map(|(_, c)| c.clone()).collect();
+    let indices = diff.contours.iter().map(|(i, _)| (*i as u64).into()).collect();
+    (layer, PlistValue::Array(indices))
+}
+
+fn layer_diff_from_layer(mut layer: Layer<MFEKPointData>, indices: &PlistValue, len: &PlistValue) -> Option<LayerDiff> {
+    let indices: Vec<usize> = indices.as_array()?.iter().map(|i| i.as_unsigned_integer().map(|i| i as usize)).collect::<Option<_>>()?;
+    if indices.len() != layer.outline.len() { return None }
+    let contours = indices.into_iter().zip(layer.outline.drain(..)).collect();
+    Some(LayerDiff { rest: layer, contours, len: len.as_unsigned_integer()? as usize })
+}
+
+impl Editor {
+    fn entry_to_plist(&self, entry: &Entry) -> Result<PlistValue, String> {
+        let dict = match entry {
+            Entry::Layer(entry) => {
+                let mut dict = common_to_plist(&entry.description, [("layer_idx", entry.layer_idx), ("contour_idx", entry.contour_idx), ("point_idx", entry.point_idx)], &entry.selected);
+                let kind = match entry.kind {
+                    HistoryType::LayerModified => "modified",
+                    HistoryType::LayerAdded => "added",
+                    HistoryType::LayerDeleted => "deleted",
+                    HistoryType::LayerMoved { to, from } => {
+                        dict.insert("to".to_string(), (to as u64).into());
+                        dict.insert("from".to_string(), (from as u64).into());
+                        "moved"
+                    }
+                };
+                dict.insert("kind".to_string(), kind.into());
+
+                let one_layer = one_layer_glif(&self.with_glyph(|g| g.name.clone()), entry.layer.clone());
+                dict.insert("glif".to_string(), write_glif_xml(one_layer)?.into());
+                dict
+            }
+            Entry::Glyph(entry) => {
+                let mut dict = common_to_plist(&entry.description, [("layer_idx", entry.layer_idx), ("contour_idx", entry.contour_idx), ("point_idx", entry.point_idx)], &entry.selected);
+                dict.insert("kind".to_string(), "glyph".into());
+                dict.insert("glif".to_string(), write_glif_xml(entry.glyph.clone())?.into());
+                dict
+            }
+            Entry::Diff(entry) => {
+                let mut dict = common_to_plist(&entry.description, [("layer_idx", entry.layer_idx), ("contour_idx", entry.contour_idx), ("point_idx", entry.point_idx)], &entry.selected);
+                match &entry.change {
+                    Change::Layer { idx, diff } => {
+                        let (layer, indices) = layer_diff_to_layer(diff);
+                        let one_layer = one_layer_glif(&self.with_glyph(|g| g.name.clone()), layer);
+                        dict.insert("kind".to_string(), "layer_diff".into());
+                        dict.insert("diff_layer".to_string(), (*idx as u64).into());
+                        dict.insert("indices".to_string(), indices);
+                        dict.insert("len".to_string(), (diff.len as u64).into());
+                        dict.insert("glif".to_string(), write_glif_xml(one_layer)?.into());
+                    }
+                    Change::Glyph { layers, rest } => {
+                        let mut glyph = (**rest).clone();
+                        let mut indices = vec![];
+                        for diff in layers {
+                            let (layer, layer_indices) = layer_diff_to_layer(diff);
+                            glyph.layers.push(layer);
+                            indices.push(layer_indices);
+                        }
+                        dict.insert("kind".to_string(), "glyph_diff".into());
+                        dict.insert("indices".to_string(), PlistValue::Array(indices));
+                        dict.insert("len".to_string(), PlistValue::Array(layers.iter().map(|d| (d.len as u64).into()).collect()));
+                        dict.insert("glif".to_string(), write_glif_xml(glyph)?.into());
+                    }
+                }
+                dict
+            }
+        };
+
+        Ok(PlistValue::Dictionary(dict))
+    }
+
+    fn entry_from_plist(value: &PlistValue) -> Option<Entry> {
+        let dict = value.as_dictionary()?;
+        let description = dict.get("description")?.as_string()?.to_owned();
+        let glyph = read_glif_xml(dict.get("glif")?.as_string()?)?;
+        let kind = match dict.get("kind")?.as_string()? {
+            "glyph" => {
+                return Some(Entry::Glyph(GlyphEntry {
+                    description,
+                    layer_idx: opt_index(dict, "layer_idx"),
+                    contour_idx: opt_index(dict, "contour_idx"),
+                    point_idx: opt_index(dict, "point_idx"),
+                    selected: selected_from_plist(dict),
+                    glyph,
+                }));
+            }
+            "layer_diff" => {
+                let layer = glyph.layers.into_iter().next()?;
+                let diff = layer_diff_from_layer(layer, dict.get("indices")?, dict.get("len")?)?;
+                return Some(Entry::Diff(DiffEntry {
+                    description,
+                    layer_idx: opt_index(dict, "layer_idx"),
+                    contour_idx: opt_index(dict, "contour_idx"),
+                    point_idx: opt_index(dict, "point_idx"),
+                    selected: selected_from_plist(dict),
+                    change: Change::Layer { idx: opt_index(dict, "diff_layer")?, diff },
+                }));
+            }
+            "glyph_diff" => {
+                let mut rest = glyph;
+                let layers = mem::take(&mut rest.layers);
+                let (indices, lens) = (dict.get("indices")?.as_array()?, dict.get("len")?.as_array()?);
+                if indices.len() != layers.len() || lens.len() != layers.len() { return None }
+                let layers = layers.into_iter().zip(indices.iter().zip(lens.iter()))
+                    .map(|(layer, (indices, len))| layer_diff_from_layer(layer, indices, len))
+                    .collect::<Option<Vec<_>>>()?;
+                return Some(Entry::Diff(DiffEntry {
+                    description,
+                    layer_idx: opt_index(dict, "layer_idx"),
+                    contour_idx: opt_index(dict, "contour_idx"),
+                    point_idx: opt_index(dict, "point_idx"),
+                    selected: selected_from_plist(dict),
+                    change: Change::Glyph { layers, rest: Box::new(rest) },
+                }));
+            }
+            "modified" => HistoryType::LayerModified,
+            "added" => HistoryType::LayerAdded,
+            "deleted" => HistoryType::LayerDeleted,
+            "moved" => HistoryType::LayerMoved { to: opt_index(dict, "to")?, from: opt_index(dict, "from")? },
+            _ => return None,
+        };
+        let layer: Layer<MFEKPointData> = glyph.layers.into_iter().next()?;
+
+        Some(Entry::Layer(HistoryEntry {
+            description,
+            layer_idx: opt_index(dict, "layer_idx"),
+            contour_idx: opt_index(dict, "contour_idx"),
+            point_idx: opt_index(dict, "point_idx"),
+            selected: selected_from_plist(dict),
+            layer,
+            kind,
+        }))
+    }
+
+    /// Serializes up to `MAX_JOURNAL_ENTRIES` of `entries`, newest kept, dropping modifications that
+    /// changed nothing.
+    fn stack_to_plist(&self, entries: &[Entry]) -> Result<PlistValue, String> {
+        let mut ret: Vec<PlistValue> = vec![];
+        for entry in entries.iter().rev() {
+            if ret.len() == MAX_JOURNAL_ENTRIES { break }
+            let value = self.entry_to_plist(entry)?;
+            // Compaction: two snapshots of the same layer, or glyph, in a row means the newer one
+            // undoes to where the older one already is.
+            let duplicate = ret.last().map(|newer| {
+                let (newer, older) = (newer.as_dictionary().unwrap(), value.as_dictionary().unwrap());
+                ["kind", "layer_idx", "glif"].iter().all(|k| newer.get(k) == older.get(k))
+            }).unwrap_or(false);
+            let snapshot = match entry {
+                Entry::Layer(entry) => matches!(entry.kind, HistoryType::LayerModified),
+                Entry::Glyph(_) => true,
+                Entry::Diff(_) => false,
+            };
+            if duplicate && snapshot { continue }
+            ret.push(value);
+        }
+        ret.reverse();
+        Ok(PlistValue::Array(ret))
+    }
+
+    /// Writes the undo and redo stacks to the journal of the glyph saved at `glif_path`.
+    pub fn write_journal(&self, glif_path: &Path) -> Result<(), MFEKglifError> {
+        let path = journal_path(glif_path);
+        let write_error = |reason: String| MFEKglifError::PlistWrite { path: path.clone(), reason };
+
+        let (undo, redo) = self.history.stacks();
+        let mut dict = Dictionary::new();
+        dict.insert("version".to_string(), JOURNAL_VERSION.into());
+        dict.insert("glif_hash".to_string(), glif_hash(glif_path).unwrap_or_default().into());
+        dict.insert("undo".to_string(), self.stack_to_plist(undo).map_err(write_error)?);
+        dict.insert("redo".to_string(), self.stack_to_plist(redo).map_err(write_error)?);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| MFEKglifError::CreateDir { path: dir.to_path_buf(), error })?;
+        }
+        PlistValue::Dictionary(dict).to_file_xml(&path).map_err(|e| write_error(e.to_string()))?;
+        log::debug!("Wrote undo journal {:?}", &path);
+        Ok(())
+    }
+
+    /// Replaces the history with the journal of the glyph at `glif_path`, if it has one that was
+    /// written with the .glif as it is now. Returns whether it did.
+    pub fn restore_journal(&mut self, glif_path: &Path) -> Result<bool, MFEKglifError> {
+        let path = journal_path(glif_path);
+        if !path.exists() { return Ok(false) }
+        let read_error = |reason: &str| MFEKglifError::PlistRead { path: path.clone(), reason: reason.to_string() };
+
+        let journal = PlistValue::from_file(&path).map_err(|e| read_error(&e.to_string()))?;
+        let journal = journal.as_dictionary().ok_or_else(|| read_error("not a dictionary"))?;
+        if !matches!(journal.get("version").and_then(PlistValue::as_unsigned_integer), Some(1..=JOURNAL_VERSION)) {
+            return Err(read_error("unknown journal version"));
+        }
+        if journal.get("glif_hash").and_then(PlistValue::as_string) != glif_hash(glif_path).as_deref() {
+            log::warn!("{:?} changed since its undo journal was written; not restoring history", glif_path);
+            return Ok(false);
+        }
+
+        let stack = |key: &str| -> Result<Vec<Entry>, MFEKglifError> {
+            journal.get(key).and_then(PlistValue::as_array).ok_or_else(|| read_error("missing stack"))?
+                .iter().map(|e| Editor::entry_from_plist(e).ok_or_else(|| read_error("bad entry"))).collect()
+        };
+        self.history = History::from_stacks(stack("undo")?, stack("redo")?);
+        log::info!("Restored undo history from {:?}", &path);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fnv1a;
+
+    #[test]
+    fn glif_hash_is_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+}
@@ -1,8 +1,61 @@
-use glifparser::glif::{MFEKOutline, HistoryEntry, HistoryType, Layer};
+use glifparser::glif::{Layer, LayerOperation, MFEKContour, MFEKOutline, MFEKPointData};
+use skulpin::skia_safe::PathOp;
+
 use super::Editor;
+use super::booleans::{closed_path, is_closed_outline, overlap_groups, path_op, to_contours};
+use super::groups::{depth, display_name, is_group, previous_sibling, set_part, subtree_end};
+
+/// The operation to merge `upper` into the layer below it with, if that can be done without changing
+/// the result. Into the root of a group, any operation applies as is. Into another operand, only the
+/// same operation regroups; two differences from one root are the difference of their union.
+fn merge_operation(lower: &Option<LayerOperation>, upper: &LayerOperation) -> Option<PathOp> {
+    match (lower, upper) {
+        (None, LayerOperation::Difference) => Some(PathOp::Difference),
+        (None, LayerOperation::Union) | (Some(LayerOperation::Difference), LayerOperation::Difference)
+            | (Some(LayerOperation::Union), LayerOperation::Union) => Some(PathOp::Union),
+        (None, LayerOperation::Intersect) | (Some(LayerOperation::Intersect), LayerOperation::Intersect) => Some(PathOp::Intersect),
+        (None, LayerOperation::XOR) | (Some(LayerOperation::XOR), LayerOperation::XOR) => Some(PathOp::XOR),
+        _ => None,
+    }
+}
+
+/// Merges the closed contours of `upper` into `lower` with `op`. Contours are grouped by overlapping
+/// bounds, and only groups with contours from both layers go through the boolean, so everything else
+/// stays as drawn, operations and all. Open contours are never part of a boolean and are kept.
+fn merge_outlines(lower: &MFEKOutline<MFEKPointData>, upper: &MFEKOutline<MFEKPointData>, op: PathOp) -> MFEKOutline<MFEKPointData> {
+    let contours: Vec<(bool, &MFEKContour<MFEKPointData>)> = lower.iter().map(|c| (false, c))
+        .chain(upper.iter().map(|c| (true, c)))
+        .filter(|(_, c)| is_closed_outline(c))
+        .collect();
+    let closed: Vec<&MFEKContour<MFEKPointData>> = contours.iter().map(|(_, c)| *c).collect();
+
+    let mut ret: MFEKOutline<MFEKPointData> = lower.iter().chain(upper.iter()).filter(|c| !is_closed_outline(c)).cloned().collect();
+    for members in overlap_groups(&closed) {
+        let from_lower: Vec<&MFEKContour<MFEKPointData>> = members.iter().filter(|&&i| !contours[i].0).map(|&i| closed[i]).collect();
+        let from_upper: Vec<&MFEKContour<MFEKPointData>> = members.iter().filter(|&&i| contours[i].0).map(|&i| closed[i]).collect();
+
+        if from_lower.is_empty() || from_upper.is_empty() {
+            // Nothing to combine with: the boolean either keeps these contours or drops them.
+            let kept = match op {
+                PathOp::Union | PathOp::XOR => true,
+                PathOp::Difference => from_upper.is_empty(),
+                _ => false,
+            };
+            if kept {
+                ret.extend(members.iter().map(|&i| closed[i].clone()));
+            }
+            continue;
+        }
+
+        if let Some(result) = path_op(&closed_path(&from_lower), &closed_path(&from_upper), op) {
+            ret.extend(to_contours(&result));
+        }
+    }
+    ret
+}
 
 impl Editor {
-    /// Adds a new layer. This generates a HistoryEntry and sets the selection to point to the newly created
+    /// Adds a new layer. This generates a history entry and sets the selection to point to the newly created
     /// layer.
     pub fn new_layer(&mut self) {
         let new_layer = Layer {
@@ -14,19 +67,11 @@ impl Editor {
             images: vec![],
         };
 
-        self.history.add_undo_entry(HistoryEntry {
-            description: "Added layer.".to_owned(),
-            layer_idx: self.layer_idx,
-            contour_idx: self.contour_idx,
-            point_idx: self.point_idx,
-            selected: Some(self.selected.clone()),
-            layer: new_layer.clone(), // dummy
-            kind: HistoryType::LayerAdded
-        });
+        self.end_layer_modification();
 
+        self.begin_glyph_modification("Added layer.");
         self.glyph.as_mut().unwrap().layers.push(new_layer);
-        
-        self.end_layer_modification();
+        self.end_glyph_modification();
 
         self.layer_idx = Some(self.glyph.as_mut().unwrap().layers.len() - 1);
         self.contour_idx = None;
@@ -35,35 +80,102 @@ impl Editor {
         self.mark_preview_dirty();
     }
 
-    /// Deletes a layer. Generates a history entry and sets the user's selection to the layer above.
+    /// Deletes a layer, with the layers in it if it's a group. Generates a history entry and sets the
+    /// user's selection to the layer above.
     pub fn delete_layer(&mut self, idx: usize, add_history: bool) {
-        if self.with_glyph(|glif| {glif.layers.len()}) == 1 { return }
+        let (end, count) = self.with_glyph(|glif| (subtree_end(&glif.layers, idx), glif.layers.len()));
+        if end - idx == count { return }
 
         self.end_layer_modification();
 
-        let deleted = self.glyph.as_mut().unwrap().layers.remove(idx);
-
         if add_history {
-            self.history.add_undo_entry(HistoryEntry {
-                description: "Deleted layer.".to_owned(),
-                layer_idx: self.layer_idx,
-                contour_idx: self.contour_idx,
-                point_idx: self.point_idx,
-                selected: Some(self.selected.clone()),
-                layer: deleted.clone(), // dummy
-                kind: HistoryType::LayerDeleted,
-            });
+            self.begin_glyph_modification("Deleted layer.");
         }
 
-        if self.layer_idx != Some(0) {
-            self.layer_idx = Some(self.layer_idx.unwrap() - 1);
+        self.glyph.as_mut().unwrap().layers.drain(idx..end);
+
+        if add_history {
+            self.end_glyph_modification();
         }
+
+        let remaining = count - (end - idx);
+        self.layer_idx = Some(self.layer_idx.unwrap().saturating_sub(1).min(remaining - 1));
         self.contour_idx = None;
         self.point_idx = None;
         self.selected.clear();
         self.mark_preview_dirty();
     }
 
+    /// Copies a layer, with the layers in it if it's a group. An operand's copy goes right after it,
+    /// in the same combination; the copy of a layer without an operation goes after the operands that
+    /// follow it, starting a new one. Generates a history entry and makes the copy active.
+    pub fn duplicate_layer(&mut self, idx: usize) {
+        self.end_layer_modification();
+
+        let (mut copy, dest) = self.with_glyph(|glif| {
+            let layers = &glif.layers;
+            let mut dest = subtree_end(layers, idx);
+            if layers[idx].operation.is_none() {
+                while dest < layers.len() && depth(&layers[dest]) == depth(&layers[idx]) && layers[dest].operation.is_some() {
+                    dest = subtree_end(layers, dest);
+                }
+            }
+            (layers[idx..subtree_end(layers, idx)].to_vec(), dest)
+        });
+        // The copy of a group is a group of its own, so its layers are renamed along with it.
+        let (d, name) = (depth(&copy[0]), format!("{} copy", display_name(&copy[0])));
+        copy.iter_mut().for_each(|l| set_part(l, d, &name));
+
+        self.begin_glyph_modification("Duplicated layer.");
+        self.glyph.as_mut().unwrap().layers.splice(dest..dest, copy);
+        self.end_glyph_modification();
+
+        self.set_active_layer(dest);
+        self.mark_preview_dirty();
+    }
+
+    /// Whether merge_layer_down can merge the layer at `idx` into the one below it: the layer must be
+    /// a visible operand, and the one below, in the same group, its root or an operand with the same
+    /// operation. Groups aren't merged.
+    pub fn can_merge_layer_down(&self, idx: usize) -> bool {
+        self.with_glyph(|glif| {
+            let upper = &glif.layers[idx];
+            let lower = match previous_sibling(&glif.layers, idx) {
+                Some(lower) if lower == idx - 1 => &glif.layers[lower],
+                _ => return false,
+            };
+            if is_group(&glif.layers, idx) || is_group(&glif.layers, idx - 1) { return false }
+            match &upper.operation {
+                Some(op) if upper.visible => merge_operation(&lower.operation, op).is_some(),
+                _ => false,
+            }
+        })
+    }
+
+    /// Applies a layer's operation to the layer below it and removes it, keeping contours the operation
+    /// doesn't touch editable. Generates a history entry and makes the merged layer active.
+    pub fn merge_layer_down(&mut self, idx: usize) {
+        if !self.can_merge_layer_down(idx) { return }
+
+        self.end_layer_modification();
+
+        let (lower, upper) = self.with_glyph(|glif| (glif.layers[idx - 1].clone(), glif.layers[idx].clone()));
+        let op = merge_operation(&lower.operation, upper.operation.as_ref().unwrap()).unwrap();
+
+        let mut merged = lower;
+        merged.outline = merge_outlines(&merged.outline, &upper.outline, op);
+        merged.images.extend(upper.images.into_iter());
+
+        self.begin_glyph_modification("Merged layer down.");
+        let layers = &mut self.glyph.as_mut().unwrap().layers;
+        layers[idx - 1] = merged;
+        layers.remove(idx);
+        self.end_glyph_modification();
+
+        self.set_active_layer(idx - 1);
+        self.mark_preview_dirty();
+    }
+
     pub fn set_active_layer(&mut self, idx: usize) {
         // TODO: save selection when leaving layer
         self.layer_idx = Some(idx);
@@ -80,25 +192,19 @@ impl Editor {
         return self.layer_idx.unwrap();
     }
     
+    /// Swaps two layers. A layer moved to the bottom loses its operation, so the history entry, if
+    /// any, is of the whole glyph.
     pub fn swap_layers(&mut self, src: usize, dest: usize, add_history: bool) {
         if add_history {
-            self.history.add_undo_entry(HistoryEntry {
-                description: "Layer moved.".to_owned(),
-                layer_idx: self.layer_idx,
-                contour_idx: self.contour_idx,
-                point_idx: self.point_idx,
-                selected: Some(self.selected.clone()),
-                layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
-                kind: HistoryType::LayerMoved { to: dest, from: src }
-            });
-    
+            self.end_layer_modification();
+            self.begin_glyph_modification("Layer moved.");
         }
 
         let src_copy = self.glyph.as_mut().unwrap().layers[src].clone();
         let dest_copy = self.glyph.as_mut().unwrap().layers[dest].clone();
 
-        if self.layer_idx == Some(dest) { self.layer_idx = Some(src) };
-        if self.layer_idx == Some(src) { self.layer_idx = Some(dest) };
+        if self.layer_idx == Some(dest) { self.layer_idx = Some(src) }
+        else if self.layer_idx == Some(src) { self.layer_idx = Some(dest) };
 
         self.glyph.as_mut().unwrap().layers[dest] = src_copy;
         self.glyph.as_mut().unwrap().layers[src] = dest_copy;
@@ -107,6 +213,9 @@ impl Editor {
             self.glyph.as_mut().unwrap().layers[dest].operation = None;
         }
 
+        if add_history {
+            self.end_glyph_modification();
+        }
         self.mark_preview_dirty();
     }
     
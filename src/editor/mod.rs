@@ -22,14 +22,22 @@ pub mod util;
 
 
 pub mod headless;
+pub mod batch;
+pub mod watch;
+pub mod check;
+pub mod font;
+pub mod journal;
 
 pub mod images;
 
 pub mod selection;
 pub mod layers;
+pub mod groups;
+pub mod booleans;
+pub mod vws_profiles;
 
 pub mod history;
-use crate::editor::history::History;
+use crate::editor::history::{Entry, History};
 
 pub mod operations;
 
@@ -49,8 +57,10 @@ pub struct Editor {
     clipboard: Option<Layer<MFEKPointData>>,
     layer_idx: Option<usize>, // active layer
     preview_dirty: bool,
+    boolean_preview: bool, // whether rebuild resolves layer operations into combined_preview
 
     pub preview: Option<MFEKGlif<MFEKPointData>>,
+    pub combined_preview: Option<Vec<operations::CombinedLayer>>,
     pub contour_idx: Option<usize>,   // index into Outline
     pub point_idx: Option<usize>, 
     pub selected: HashSet<(usize, usize)>,
@@ -60,6 +70,12 @@ pub struct Editor {
     pub quit_requested: bool, // allows for quits from outside event loop, e.g. from command closures
 
     pub ipc_info: Option<mfek_ipc::IPCInfo>,
+
+    pub font: Option<font::FontSession>, // set when the glyph is part of a UFO we can navigate
+    pub journal: bool, // keep the undo history on disk, see journal.rs
+
+    pub vws_profile: Option<vws_profiles::VWSProfile>, // the copied VWS profile
+    vws_library: Option<Result<std::collections::BTreeMap<String, vws_profiles::VWSProfile>, String>>, // read when first needed
 }
 
 impl Editor {
@@ -76,6 +92,7 @@ impl Editor {
             clipboard: None,
             layer_idx: None,
             preview: None,
+            combined_preview: None,
 
             contour_idx: None,
             point_idx: None,
@@ -85,7 +102,12 @@ impl Editor {
             images: images::EditorImages::new(),
             quit_requested: false,
             ipc_info: None,
+            font: None,
+            journal: false,
+            vws_profile: None,
+            vws_library: None,
             preview_dirty: true,
+            boolean_preview: false,
         }
     }
     
@@ -116,7 +138,7 @@ impl Editor {
     pub fn begin_layer_modification(&mut self, description: &str) {
         if self.modifying == true { panic!("Began a new modification with one in progress!")}
 
-        self.history.add_undo_entry(HistoryEntry {
+        self.history.add_undo_entry(Entry::Layer(HistoryEntry {
             description: description.to_owned(),
             layer_idx: self.layer_idx,
             contour_idx: self.contour_idx,
@@ -124,7 +146,19 @@ impl Editor {
             selected: Some(self.selected.clone()),
             layer: self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()].clone(),
             kind: HistoryType::LayerModified
-        });
+        }));
+
+        self.modifying = true;
+    }
+
+    /// Like begin_layer_modification, for changes that aren't to the active layer alone: anchors,
+    /// guidelines, width, or several layers at once. Pushes a copy of the whole glyph onto the history
+    /// stack; make the changes with with_glyph_mut and finish with end_glyph_modification.
+    pub fn begin_glyph_modification(&mut self, description: &str) {
+        if self.modifying == true { panic!("Began a new modification with one in progress!")}
+
+        let entry = self.glyph_entry(description);
+        self.history.add_undo_entry(Entry::Glyph(entry));
 
         self.modifying = true;
     }
@@ -148,9 +182,14 @@ impl Editor {
 
         // TODO: Events here.
         self.modifying = false;
+        self.compact_history();
         self.mark_preview_dirty();
     }
 
+    pub fn end_glyph_modification(&mut self) {
+        self.end_layer_modification();
+    }
+
     pub fn is_modifying(&self) -> bool {
         self.modifying
     }
@@ -167,7 +206,9 @@ impl Editor {
         closure(&self.glyph.as_ref().unwrap())
     }
 
-    /// This function should not be called in tools or contour operations. TODO: Remove.
+    /// Calls the closure with a mutable reference to the glif. Changes made outside of
+    /// begin_glyph_modification and end_glyph_modification can't be undone, so only do that for state
+    /// that isn't the user's, like guidelines from the font's metrics.
     pub fn with_glyph_mut<F, R>(&mut self, mut closure: F) -> R 
         where F: FnMut(&mut MFEKGlif<MFEKPointData>) -> R 
     {
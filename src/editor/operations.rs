@@ -1,10 +1,18 @@
-use glifparser::{MFEKGlif, Outline, glif::{Layer, LayerOperation, MFEKPointData}};
-use glifparser::outline::skia::{FromSkiaPath, ToSkiaPaths};
-use skulpin::skia_safe::{Path, PathOp};
+use glifparser::{MFEKGlif, Outline, glif::{Layer, MFEKPointData}};
+use glifparser::outline::skia::FromSkiaPath;
+use skulpin::skia_safe::{Matrix, Path};
 
 use crate::contour_operations;
 
-use super::Editor;
+use super::{Editor, calc_x, calc_y};
+use super::groups;
+
+/// A top-level layer group with its layer operations resolved, as the boolean preview draws it.
+pub struct CombinedLayer {
+    /// In Skia units, see `calc_x` and `calc_y`.
+    pub path: Path,
+    pub color: Option<[f32; 4]>,
+}
 
 impl Editor {
     pub fn mark_preview_dirty(&mut self)
@@ -12,6 +20,57 @@ impl Editor {
         self.preview_dirty = true;
     }
 
+    pub fn boolean_preview(&self) -> bool {
+        self.boolean_preview
+    }
+
+    /// Turns the boolean preview on or off. While it's on, every rebuild also resolves the layer
+    /// operations, so the canvas can show the glyph as it will export.
+    pub fn set_boolean_preview(&mut self, on: bool) {
+        self.boolean_preview = on;
+        self.mark_preview_dirty();
+    }
+
+    /// The contours apply_contour_operations would change: the selected ones with an operation, or
+    /// every one with an operation if nothing is selected.
+    fn contours_to_apply(&self) -> Vec<usize> {
+        let selected = self.selected_contours();
+        self.with_active_layer(|layer| {
+            layer.outline.iter().enumerate()
+                .filter(|(ci, c)| c.operation.is_some() && (selected.is_empty() || selected.contains(ci)))
+                .map(|(ci, _)| ci)
+                .collect()
+        })
+    }
+
+    pub fn can_apply_contour_operations(&self) -> bool {
+        !self.contours_to_apply().is_empty()
+    }
+
+    /// Replaces contours' operations with what they build, e.g. a stroke with its outline, so the
+    /// result can be edited by hand. Works on the selected contours, or on all of the active layer if
+    /// nothing is selected. Generates a history entry, and returns false without one if no contour had
+    /// an operation.
+    pub fn apply_contour_operations(&mut self) -> bool {
+        if self.is_modifying() { return false }
+        let to_apply = self.contours_to_apply();
+        if to_apply.is_empty() { return false }
+
+        self.begin_layer_modification("Applied contour operation.");
+        self.with_active_layer_mut(|layer| {
+            for &ci in to_apply.iter().rev() {
+                let built = contour_operations::build(&layer.outline[ci]);
+                layer.outline.splice(ci..=ci, built);
+            }
+        });
+        self.end_layer_modification();
+
+        self.contour_idx = None;
+        self.point_idx = None;
+        self.selected.clear();
+        true
+    }
+
     pub fn rebuild(&mut self) {
         if !self.preview_dirty {return};
 
@@ -20,8 +79,9 @@ impl Editor {
         }
 
         //self.fix_contour_ops();
+        let layers = &self.glyph.as_ref().unwrap().layers;
         let mut preview_layers = Vec::new();
-        for layer in &self.glyph.as_ref().unwrap().layers {
+        for (layer_idx, layer) in layers.iter().enumerate() {
             let mut preview_outline = Vec::new();
 
             for (_idx, glif_contour) in layer.outline.iter().enumerate() {
@@ -36,69 +96,80 @@ impl Editor {
 
             let mut new_layer = layer.clone();
             new_layer.outline = preview_outline;
+            // A layer in a hidden group is hidden too.
+            new_layer.visible = groups::is_shown(layers, layer_idx);
             preview_layers.push(new_layer);
         }
 
         self.preview = Some(self.glyph.as_ref().unwrap().clone());
         self.preview.as_mut().unwrap().layers = preview_layers;
+
+        self.combined_preview = if self.boolean_preview {
+            let to_skia = Matrix::new_all(1., 0., calc_x(0.), 0., -1., calc_y(0.), 0., 0., 1.);
+            Some(combine_layer_groups(self.preview.as_ref().unwrap()).into_iter().map(|(layer, mut path)| {
+                path.transform(&to_skia);
+                CombinedLayer { path, color: layer.color.map(|c| c.into()) }
+            }).collect())
+        } else {
+            None
+        };
         self.preview_dirty = false;
     }
 
     pub fn prepare_export(&self) -> MFEKGlif<MFEKPointData>
     {
         let glif = self.preview.as_ref().unwrap();
-    
-        // MFEKGlif always has a layer zero so this is safe. (No it isn't, it can be invisible. TODO: Fix this.)
-        let mut last_combine_layer: Layer<MFEKPointData> = glif.layers[0].clone();
-        let mut exported_layers: Vec<Layer<MFEKPointData>> = vec![];
-        let new_combine_paths = last_combine_layer.outline.to_skia_paths(None).closed;
-        let mut current_layer_group = new_combine_paths.unwrap_or(Path::new());
-
-        for (layer_idx, layer) in glif.layers.iter().enumerate() {
-            if !layer.visible { continue; }
-            if layer_idx == 0 { continue; }
-    
-            let skpaths = layer.outline.to_skia_paths(None);
-
-            match &layer.operation {
-                Some(op) => {
-                    let pathop = match op {
-                        LayerOperation::Difference  => PathOp::Difference,
-                        LayerOperation::Union  => PathOp::Union,
-                        LayerOperation::Intersect => PathOp::Intersect,
-                        LayerOperation::XOR => PathOp::XOR
-                    };
-        
-                    if let Some(closed) = skpaths.closed {
-                        if let Some(result) = current_layer_group.op(&closed, pathop).unwrap().as_winding() {
-                            current_layer_group = result;
-                        }
-                    }
-                }
-                
-                None => {
-                    let mut combined_layer = last_combine_layer.clone();
-                    last_combine_layer = layer.clone();
-
-                    let combined_layer_outline = Outline::from_skia_path(&current_layer_group);
-                    let mfek_outline = combined_layer_outline.iter().map(|c| c.into()).collect();
-                    combined_layer.outline = mfek_outline;
-                    exported_layers.push(combined_layer);
-
-                    let new_combine_paths = layer.outline.to_skia_paths(None).closed;
-                    current_layer_group = new_combine_paths.unwrap_or(Path::new());
-                }
-            }
-        }
 
-        let mut combined_layer = last_combine_layer.clone();
-        let combined_layer_outline = Outline::from_skia_path(&current_layer_group);
-        let mfek_outline = combined_layer_outline.iter().map(|c| c.into()).collect();
-        combined_layer.outline = mfek_outline;
-        exported_layers.push(combined_layer);
+        let exported_layers = combine_layer_groups(glif).into_iter().map(|(mut combined_layer, path)| {
+            let combined_layer_outline = Outline::from_skia_path(&path);
+            let mfek_outline = combined_layer_outline.iter().map(|c| c.into()).collect();
+            combined_layer.outline = mfek_outline;
+            combined_layer
+        }).collect();
 
         let mut exported_mfek = glif.clone();
         exported_mfek.layers = exported_layers;
         return exported_mfek;
     }
 }
+
+/// Resolves the layer operations of a built glyph. Returns, for each top-level layer group, its
+/// first layer and the closed outline of the whole group.
+fn combine_layer_groups(glif: &MFEKGlif<MFEKPointData>) -> Vec<(Layer<MFEKPointData>, Path)> {
+    // MFEKGlif always has a layer zero so this is safe. (No it isn't, it can be invisible. TODO: Fix this.)
+    let mut last_combine_layer: Layer<MFEKPointData> = glif.layers[0].clone();
+    let mut combined_layers = vec![];
+    let new_combine_paths = groups::combined_path(&glif.layers, 0);
+    let mut current_layer_group = new_combine_paths.unwrap_or(Path::new());
+
+    // Layers nested in groups are combined into their group's path; only the top level becomes
+    // exported layers.
+    for (layer_idx, layer) in glif.layers.iter().enumerate() {
+        if !layer.visible { continue; }
+        if layer_idx == 0 { continue; }
+        if groups::depth(layer) > 0 { continue; }
+
+        let closed = groups::combined_path(&glif.layers, layer_idx);
+
+        match &layer.operation {
+            Some(op) => {
+                let pathop = groups::path_op(op);
+
+                if let Some(closed) = closed {
+                    if let Some(result) = current_layer_group.op(&closed, pathop).unwrap().as_winding() {
+                        current_layer_group = result;
+                    }
+                }
+            }
+
+            None => {
+                combined_layers.push((last_combine_layer, current_layer_group));
+                last_combine_layer = layer.clone();
+                current_layer_group = closed.unwrap_or(Path::new());
+            }
+        }
+    }
+
+    combined_layers.push((last_combine_layer, current_layer_group));
+    combined_layers
+}
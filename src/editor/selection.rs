@@ -6,8 +6,9 @@ use super::Editor;
 use super::util::is_point_selected;
 
 impl Editor {
-    /// Copy the current selection and put it in our clipboard. 
-    pub fn copy_selection(&mut self) {        
+    /// Cuts the active layer's contours at the selection, keeping either the selected or the
+    /// unselected points. Contours that were cut become open.
+    fn split_selection(&self, keep_selected: bool) -> Vec<MFEKContour<MFEKPointData>> {
         let layer = &self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()];
         let mut new_outline: Vec<MFEKContour<MFEKPointData>> = Vec::new();
         for (contour_idx, contour) in layer.outline.iter().enumerate() {
@@ -18,7 +19,7 @@ impl Editor {
 
             let mut deleted = false;
             for (point_idx, point) in contour.inner.iter().enumerate() {
-                let to_delete = !is_point_selected(self, contour_idx, point_idx);
+                let to_delete = is_point_selected(self, contour_idx, point_idx) != keep_selected;
 
                 if to_delete {
                     let mut mfekcur: MFEKContour<MFEKPointData> = cur_contour.into();
@@ -52,6 +53,13 @@ impl Editor {
                 }
             }
         }
+        new_outline
+    }
+
+    /// Copy the current selection and put it in our clipboard. 
+    pub fn copy_selection(&mut self) {        
+        let new_outline = self.split_selection(true);
+        let layer = &self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()];
 
         self.clipboard = Some(Layer{
             name: "".to_string(),
@@ -85,57 +93,40 @@ impl Editor {
     pub fn delete_selection(&mut self) {
         self.begin_layer_modification("Delete selection.");
         
-        let layer = &self.glyph.as_ref().unwrap().layers[self.layer_idx.unwrap()];
-        let mut new_outline: Vec<MFEKContour<MFEKPointData>> = Vec::new();
-        for (contour_idx, contour) in layer.outline.iter().enumerate() {
-            let mut results = Vec::new();
-            let mut cur_contour = Vec::new();
+        let new_outline = self.split_selection(false);
+        self.glyph.as_mut().unwrap().layers[self.layer_idx.unwrap()].outline = new_outline;
 
-            let mut begin = 0;
+        self.end_layer_modification();
 
-            let mut deleted = false;
-            for (point_idx, point) in contour.inner.iter().enumerate() {
-                let to_delete = is_point_selected(self, contour_idx, point_idx);
+        self.contour_idx = None;
+        self.point_idx = None;
+        self.selected.clear();
+    }
 
-                if to_delete {
-                    let mut mfekcur: MFEKContour<MFEKPointData> = cur_contour.into();
-                    mfekcur.operation = contour_operations::sub(&contour, begin, point_idx);
-                    results.push(mfekcur);
+    /// Moves or copies the selection to the layer at `dest` as one history entry, and makes that
+    /// layer active with the transferred points selected.
+    pub fn transfer_selection(&mut self, dest: usize, remove: bool) {
+        if (self.selected.is_empty() && self.point_idx.is_none()) || self.layer_idx == Some(dest) { return }
 
-                    cur_contour = Vec::new();
-                    deleted = true;
-                    begin = point_idx + 1;
-                } else  {
-                    cur_contour.push(point.clone());
-                }
-            }
-            let mut mfekcur: MFEKContour<MFEKPointData> = cur_contour.into();
-            mfekcur.operation = contour_operations::sub(&contour, begin, contour.inner.len() - 1);
-            results.push(mfekcur);
+        self.end_layer_modification();
+        self.begin_glyph_modification(if remove { "Moved selection to layer." } else { "Copied selection to layer." });
 
-            if results.len() > 1 && contour.inner.first().unwrap().ptype != PointType::Move {
-                let mut move_to_front = results.pop().unwrap().clone();
-                move_to_front.inner.append(&mut results[0].inner);
-                move_to_front.operation = contour_operations::append(&move_to_front, &results[0]);
-                results[0] = move_to_front;
-            }
+        let transferred = self.split_selection(true);
+        if remove {
+            let rest = self.split_selection(false);
+            self.glyph.as_mut().unwrap().layers[self.layer_idx.unwrap()].outline = rest;
+        }
 
-            for mut result in results {
-                if result.inner.len() != 0 {
-                    if deleted {
-                        result.inner.first_mut().unwrap().ptype = PointType::Move;
-                    }
-                    new_outline.push(result); 
-                }
+        self.set_active_layer(dest);
+        let layer = &mut self.glyph.as_mut().unwrap().layers[dest];
+        for contour in transferred {
+            let cur_idx = layer.outline.len();
+            for (point_selection, _) in contour.inner.iter().enumerate() {
+                self.selected.insert((cur_idx, point_selection));
             }
+            layer.outline.push(contour);
         }
 
-        self.glyph.as_mut().unwrap().layers[self.layer_idx.unwrap()].outline = new_outline;
-
-        self.end_layer_modification();
-
-        self.contour_idx = None;
-        self.point_idx = None;
-        self.selected.clear();
+        self.end_glyph_modification();
     }
 }
@@ -0,0 +1,211 @@
+//! VWS profiles: the variable width stroke of one contour, copied to go on others. A profile holds
+//! the contour it came from, so that it can be spread over a contour with a different number of
+//! points (see `resample`).
+//!
+//! Profiles can be kept in a library, `vws_profiles.plist` in the configuration directory, to be
+//! used across glyphs and sessions. It is a plist of profile names to one-layer MFEK .glifs, as
+//! the undo journal writes them, whose only contour is the one the profile was copied from. A
+//! library that can't be read is never written over: until it is fixed or removed, saving and
+//! deleting profiles fail with the error that reading it gave.
+
+use super::Editor;
+use super::booleans::is_open;
+use super::journal::{read_glif_xml, write_glif_xml};
+
+use MFEKmath::Piecewise;
+use glifparser::Glif;
+use glifparser::glif::{ContourOperations, Layer, MFEKContour, MFEKGlif, MFEKPointData, VWSContour};
+use plist::{Dictionary, Value as PlistValue};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::contour_operations::variablewidthstroke::resample;
+use crate::settings::CONFIG_PATH;
+use crate::util::error::MFEKglifError;
+
+const LIBRARY_VERSION: u64 = 1;
+
+fn library_path() -> PathBuf {
+    CONFIG_PATH.join("vws_profiles.plist")
+}
+
+fn segments(contour: &MFEKContour<MFEKPointData>) -> usize {
+    Piecewise::from(contour).segs.len()
+}
+
+#[derive(Clone)]
+pub struct VWSProfile {
+    contour: MFEKContour<MFEKPointData>,
+}
+
+impl VWSProfile {
+    /// The profile of `contour`, None if it isn't stroked.
+    pub fn from_contour(contour: &MFEKContour<MFEKPointData>) -> Option<Self> {
+        match contour.operation {
+            Some(ContourOperations::VariableWidthStroke { .. }) => Some(VWSProfile { contour: contour.clone() }),
+            _ => None,
+        }
+    }
+
+    pub fn vws(&self) -> &VWSContour {
+        match &self.contour.operation {
+            Some(ContourOperations::VariableWidthStroke { data }) => data,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The stroke for `contour`: the profile as it is if their points match, resampled if not.
+    pub fn fit(&self, contour: &MFEKContour<MFEKPointData>) -> VWSContour {
+        let (from, to) = (segments(&self.contour), segments(contour));
+        if from == to && is_open(&self.contour) == is_open(contour) {
+            let mut vws = self.vws().clone();
+            if let Some(last) = vws.handles.last().cloned() {
+                vws.handles.resize(contour.inner.len() + 1, last);
+            }
+            return vws;
+        }
+        resample(self.vws(), from, to, contour.inner.len())
+    }
+}
+
+// A one-layer glif whose only contour is the one `profile` was copied from.
+fn profile_glif(name: &str, profile: &VWSProfile) -> MFEKGlif<MFEKPointData> {
+    let mut glif: MFEKGlif<MFEKPointData> = Glif::new().into();
+    glif.name = name.to_string();
+    glif.layers = vec![Layer {
+        name: name.to_string(),
+        visible: true,
+        color: None,
+        outline: vec![profile.contour.clone()],
+        operation: None,
+        images: vec![],
+    }];
+    glif
+}
+
+// The library, or why it can't be read.
+fn read_library() -> Result<BTreeMap<String, VWSProfile>, String> {
+    let path = library_path();
+    if !path.exists() { return Ok(BTreeMap::new()) }
+
+    let library = PlistValue::from_file(&path).map_err(|e| e.to_string())?;
+    if !matches!(library.as_dictionary().and_then(|d| d.get("version")).and_then(PlistValue::as_unsigned_integer), Some(1..=LIBRARY_VERSION)) {
+        return Err("unknown version".to_string());
+    }
+
+    let profiles = library.as_dictionary().and_then(|d| d.get("profiles")).and_then(PlistValue::as_dictionary).ok_or_else(|| "missing profiles".to_string())?;
+    let mut ret = BTreeMap::new();
+    for (name, glif) in profiles {
+        let profile = glif.as_string()
+            .and_then(read_glif_xml)
+            .and_then(|glif| glif.layers.into_iter().next())
+            .and_then(|layer| layer.outline.into_iter().next())
+            .and_then(|contour| VWSProfile::from_contour(&contour));
+        let profile = profile.ok_or_else(|| format!("unreadable profile {:?}", name))?;
+        ret.insert(name.clone(), profile);
+    }
+    Ok(ret)
+}
+
+impl Editor {
+    /// Copies the stroke of the contour at contour_idx of the active layer. Returns false if it
+    /// isn't stroked.
+    pub fn copy_vws_profile(&mut self) -> bool {
+        let profile = match self.contour_idx {
+            Some(ci) => self.with_active_layer(|layer| layer.outline.get(ci).and_then(VWSProfile::from_contour)),
+            None => None,
+        };
+        match profile {
+            Some(profile) => { self.vws_profile = Some(profile); true }
+            None => false,
+        }
+    }
+
+    /// Strokes the selected contours of the active layer, or the contour at contour_idx if none are,
+    /// with the copied profile, replacing any operation they had. Generates a history entry, and
+    /// returns false without one if there was no profile or nothing to stroke.
+    pub fn paste_vws_profile(&mut self) -> bool {
+        if self.is_modifying() { return false }
+        let profile = match &self.vws_profile {
+            Some(profile) => profile.clone(),
+            None => return false,
+        };
+
+        let mut targets = self.selected_contours();
+        if targets.is_empty() { targets.extend(self.contour_idx) }
+        let targets: Vec<usize> = self.with_active_layer(|layer| targets.iter().copied().filter(|&ci| ci < layer.outline.len()).collect());
+        if targets.is_empty() { return false }
+
+        self.begin_layer_modification("Pasted VWS profile.");
+        self.with_active_layer_mut(|layer| {
+            for &ci in &targets {
+                let data = profile.fit(&layer.outline[ci]);
+                layer.outline[ci].operation = Some(ContourOperations::VariableWidthStroke { data });
+            }
+        });
+        self.end_layer_modification();
+        true
+    }
+
+    // The library, read the first time it's needed. If it couldn't be read, the error is kept, so
+    // the file isn't read again every frame and nothing overwrites it.
+    fn vws_library(&mut self) -> Result<&mut BTreeMap<String, VWSProfile>, MFEKglifError> {
+        if self.vws_library.is_none() {
+            self.vws_library = Some(read_library());
+        }
+        match self.vws_library.as_mut().unwrap() {
+            Ok(library) => Ok(library),
+            Err(reason) => Err(MFEKglifError::PlistRead { path: library_path(), reason: reason.clone() }),
+        }
+    }
+
+    /// The names of the profiles in the library, in order.
+    pub fn vws_profile_names(&mut self) -> Result<Vec<String>, MFEKglifError> {
+        Ok(self.vws_library()?.keys().cloned().collect())
+    }
+
+    /// Copies the profile called `name` from the library. Returns false if there is none.
+    pub fn load_vws_profile(&mut self, name: &str) -> Result<bool, MFEKglifError> {
+        match self.vws_library()?.get(name).cloned() {
+            Some(profile) => { self.vws_profile = Some(profile); Ok(true) }
+            None => Ok(false),
+        }
+    }
+
+    /// Adds the copied profile to the library as `name`, replacing any of that name, and writes the
+    /// library. Does nothing if no profile is copied.
+    pub fn save_vws_profile(&mut self, name: &str) -> Result<(), MFEKglifError> {
+        let profile = match self.vws_profile.clone() {
+            Some(profile) => profile,
+            None => return Ok(()),
+        };
+        self.vws_library()?.insert(name.to_string(), profile);
+        self.write_vws_library()
+    }
+
+    /// Removes the profile called `name` from the library and writes the library. Returns false if
+    /// there was none.
+    pub fn delete_vws_profile(&mut self, name: &str) -> Result<bool, MFEKglifError> {
+        if self.vws_library()?.remove(name).is_none() { return Ok(false) }
+        self.write_vws_library().map(|_| true)
+    }
+
+    fn write_vws_library(&mut self) -> Result<(), MFEKglifError> {
+        let path = library_path();
+        let write_error = |reason: String| MFEKglifError::PlistWrite { path: path.clone(), reason };
+
+        let mut profiles = Dictionary::new();
+        for (name, profile) in self.vws_library()?.iter() {
+            profiles.insert(name.clone(), write_glif_xml(profile_glif(name, profile)).map_err(&write_error)?.into());
+        }
+
+        let mut library = Dictionary::new();
+        library.insert("version".to_string(), LIBRARY_VERSION.into());
+        library.insert("profiles".to_string(), PlistValue::Dictionary(profiles));
+
+        fs::create_dir_all(&*CONFIG_PATH).map_err(|error| MFEKglifError::CreateDir { path: CONFIG_PATH.clone(), error })?;
+        PlistValue::Dictionary(library).to_file_xml(&path).map_err(|e| write_error(e.to_string()))
+    }
+}
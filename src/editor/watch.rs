@@ -0,0 +1,97 @@
+//! `--export --watch`: exports a glyph, then exports it again every time it or one of its images
+//! changes on disk, so the flattened layers in the UFO follow a master that other tools generate.
+
+use super::Editor;
+
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use crate::io as glif_io;
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
+
+// Many programs save by writing a temporary file and renaming it over the old one; wait for that to
+// settle before reloading.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Editor {
+    /// The files the glyph is built from: its .glif and the images placed on its layers. Images are
+    /// looked up in the UFO's images directory, as the UFO spec puts them.
+    fn watched_files(&self, glif_path: &Path) -> Vec<PathBuf> {
+        let mut ret = vec![canonical(glif_path)];
+        let images_dir = canonical(glif_path).parent().and_then(Path::parent).map(|ufo| ufo.join("images"));
+        if let Some(images_dir) = images_dir {
+            self.with_glyph(|glif| {
+                for layer in &glif.layers {
+                    for (image, _matrix) in &layer.images {
+                        ret.push(canonical(&images_dir.join(&image.filename)));
+                    }
+                }
+            });
+        }
+        ret
+    }
+
+    /// Never returns unless watching itself fails. Failures to reload or export are logged and the
+    /// glyph is watched on, since the next save will likely fix them.
+    pub fn watch_and_export(&mut self) -> Result<(), MFEKglifError> {
+        let glif_path = self.with_glyph(|g| g.filename.clone()).ok_or(MFEKglifError::NoFileChosen)?;
+        let watch_error = |reason: String| MFEKglifError::Watch { path: glif_path.clone(), reason };
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::watcher(tx, DEBOUNCE).map_err(|e| watch_error(e.to_string()))?;
+        let mut watched_dirs = HashSet::new();
+
+        if let Err(e) = self.export_glif() {
+            messages::emit(Message::Error { error: &e });
+        }
+
+        loop {
+            // Taken after exporting, so that our own writes into the UFO don't count as changes.
+            let seen: HashMap<PathBuf, Option<SystemTime>> = self.watched_files(&glif_path).into_iter().map(|f| {
+                let m = modified(&f);
+                (f, m)
+            }).collect();
+
+            // Directories rather than files, so a file replaced by a rename is still watched.
+            for dir in seen.keys().filter_map(|f| f.parent()) {
+                if watched_dirs.insert(dir.to_path_buf()) {
+                    watcher.watch(dir, RecursiveMode::NonRecursive).unwrap_or_else(|e| log::warn!("Cannot watch {:?}: {}", dir, e));
+                }
+            }
+            log::info!("Watching {:?} and {} image(s) for changes", &glif_path, seen.len() - 1);
+
+            loop {
+                let changed = match rx.recv().map_err(|e| watch_error(e.to_string()))? {
+                    DebouncedEvent::Create(p) | DebouncedEvent::Write(p) | DebouncedEvent::Chmod(p) | DebouncedEvent::Rename(_, p) => canonical(&p),
+                    DebouncedEvent::Error(e, p) => { log::warn!("Watch error on {:?}: {}", p, e); continue },
+                    _ => continue,
+                };
+                match seen.get(&changed) {
+                    Some(old) if modified(&changed) != *old => {
+                        log::info!("{:?} changed, exporting again", &changed);
+                        break
+                    }
+                    _ => continue,
+                }
+            }
+
+            if let Err(e) = glif_io::load_glif_headless(self, &glif_path).and_then(|()| self.export_glif()) {
+                messages::emit(Message::Error { error: &e });
+            }
+        }
+    }
+}
@@ -2,17 +2,16 @@ use std::path::PathBuf;
 
 use nfd;
 
-pub fn filename_or_panic(
+use crate::util::error::MFEKglifError;
+
+pub fn filename_or_dialog(
     filename: &Option<String>,
     filter: Option<&str>,
     start_in: Option<&str>,
-) -> PathBuf {
+) -> Result<PathBuf, MFEKglifError> {
     match filename {
-        Some(file) => file.into(),
-        None => match nfd::open_file_dialog(filter, start_in) {
-            Ok(nfd::Response::Okay(file)) => file.into(),
-            _ => panic!("Exit requested"),
-        },
+        Some(file) => Ok(file.into()),
+        None => open_filename(filter, start_in).ok_or(MFEKglifError::NoFileChosen),
     }
 }
 
@@ -23,6 +22,13 @@ pub fn open_filename(filter: Option<&str>, start_in: Option<&str>) -> Option<Pat
     }
 }
 
+pub fn open_folder(start_in: Option<&str>) -> Option<PathBuf> {
+    match nfd::open_pick_folder(start_in) {
+        Ok(nfd::Response::Okay(folder)) => Some(folder.into()),
+        Ok(_) | Err(_) => None,
+    }
+}
+
 pub fn save_filename(filter: Option<&str>, start_in: Option<&str>) -> Option<PathBuf> {
     match nfd::open_save_dialog(filter, start_in) {
         Ok(nfd::Response::Okay(file)) => Some(file.into()),
@@ -1,6 +1,10 @@
+use crate::filedialog;
 use crate::ipc;
 use crate::editor::Editor;
+use crate::editor::font::FontSession;
 use crate::util::DEBUG_DUMP_GLYPH;
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
 use crate::user_interface::Interface;
 
 use glifparser::{MFEKGlif, glif::MFEKPointData};
@@ -8,20 +12,145 @@ use log::debug;
 
 use std::path::Path;
 
-pub fn load_glif<F: AsRef<Path> + Clone>(v: &mut Editor, i: &mut Interface, filename: F) {
-    i.set_window_title(&format!("MFEKglif — {}", filename.as_ref().to_str().unwrap() )).expect("Failed to set SDL2 window title");
-    load_glif_headless(v, filename);
+pub fn load_glif<F: AsRef<Path> + Clone>(v: &mut Editor, i: &mut Interface, filename: F) -> Result<(), MFEKglifError> {
+    load_glif_headless(v, filename.clone())?;
+    set_title(i, filename.as_ref());
+    Ok(())
 }
 
-pub fn load_glif_headless<F: AsRef<Path> + Clone>(v: &mut Editor, filename: F) {
-    // TODO: Actually handle errors now that we have them.
-    let glif: MFEKGlif<MFEKPointData> = glifparser::read_from_filename(&filename).expect("Invalid glif!").into();
+/// Reads the .glif and only then replaces the editor's glyph, so a bad file leaves the current
+/// session as it was.
+pub fn load_glif_headless<F: AsRef<Path> + Clone>(v: &mut Editor, filename: F) -> Result<(), MFEKglifError> {
+    let glif = read_glif(&filename)?;
+    install_glif(v, glif, filename.as_ref());
+    Ok(())
+}
+
+fn read_glif<F: AsRef<Path>>(filename: F) -> Result<MFEKGlif<MFEKPointData>, MFEKglifError> {
+    let glif: MFEKGlif<MFEKPointData> = glifparser::read_from_filename(&filename).map_err(|e| MFEKglifError::GlifRead {
+        path: filename.as_ref().to_path_buf(),
+        reason: format!("{:?}", e),
+    })?.into();
 
     if *DEBUG_DUMP_GLYPH {
         debug!("{:#?}", &glif.clone());
     }
 
+    Ok(glif)
+}
+
+// Makes a freshly read glyph the editor's, with its own history.
+fn install_glif(v: &mut Editor, glif: MFEKGlif<MFEKPointData>, filename: &Path) {
     v.set_glyph(glif);
+    v.reset_history();
+    if v.journal {
+        v.restore_journal(filename).map(drop).unwrap_or_else(|e| log::warn!("{}", e));
+    }
 
     ipc::fetch_metrics(v);
-}
\ No newline at end of file
+
+    messages::emit(Message::Loaded { path: filename });
+}
+
+fn set_title(i: &mut Interface, path: &Path) {
+    i.set_window_title(&format!("MFEKglif — {}", path.to_str().unwrap())).expect("Failed to set SDL2 window title");
+}
+
+// Leaving the open UFO drops its parked glyphs, so refuse while any of them have edits.
+fn check_parked_saved(v: &Editor) -> Result<(), MFEKglifError> {
+    let edited = match v.font.as_ref() {
+        Some(font) => font.edited_glyphs(),
+        None => return Ok(()),
+    };
+    if edited.is_empty() { return Ok(()) }
+    Err(MFEKglifError::UnsavedGlyphs(edited.join(", ")))
+}
+
+/// Opens a .glif, or the first glyph of a .ufo. If the glyph is in a UFO, the UFO's other glyphs
+/// can then be reached with `switch_glyph`. Opening anything outside the UFO already open fails
+/// while its parked glyphs have unsaved changes, and nothing is replaced until the new glyph has
+/// been read.
+pub fn load_glif_or_ufo<F: AsRef<Path>>(v: &mut Editor, i: &mut Interface, path: F) -> Result<(), MFEKglifError> {
+    let path = path.as_ref();
+
+    // The UFO or another glyph of the UFO we already have open: keep the session and its parked
+    // glyphs.
+    if let Some(font) = v.font.as_ref() {
+        let same_ufo = path.is_dir() && font.ufo.canonicalize().ok() == path.canonicalize().ok();
+        let idx = if same_ufo { Some(0) } else { font.index_of(path) };
+        if let Some(idx) = idx {
+            return switch_glyph(v, i, idx);
+        }
+    }
+
+    check_parked_saved(v)?;
+
+    if path.is_dir() {
+        let session = FontSession::open(path)?;
+        let first = session.glyphs[0].1.clone();
+        let glif = read_glif(&first)?;
+        v.font = Some(session);
+        install_glif(v, glif, &first);
+        set_title(i, &first);
+        return Ok(());
+    }
+
+    load_glif(v, i, path)?;
+    v.font = FontSession::for_glif(path);
+    Ok(())
+}
+
+/// Saves the glyph to a file the user picks, which becomes its file; its history and selection stay
+/// as they are. Saved over another glyph of the open UFO, it becomes that glyph, which is refused if
+/// that glyph was left with unsaved changes. Saved anywhere else, it leaves the UFO, which as with
+/// opening is refused while any glyph left in it has unsaved changes.
+pub fn save_glif_as(v: &mut Editor, i: &mut Interface) -> Result<(), MFEKglifError> {
+    if v.is_modifying() { return Ok(()) }
+    let path = filedialog::save_filename(Some("glif"), None).ok_or(MFEKglifError::NoFileChosen)?;
+
+    let idx = v.font.as_ref().and_then(|font| font.index_of(&path));
+    match (idx, v.font.as_ref()) {
+        (Some(idx), Some(font)) if idx != font.current && font.is_edited(idx) => {
+            return Err(MFEKglifError::UnsavedGlyphs(font.glyphs[idx].0.clone()));
+        }
+        (None, _) => check_parked_saved(v)?,
+        _ => (),
+    }
+
+    v.save_glif_as(&path)?;
+    match idx {
+        Some(idx) => v.font.as_mut().unwrap().replace_with_current(idx),
+        None => v.font = FontSession::for_glif(&path),
+    }
+
+    set_title(i, &path);
+    Ok(())
+}
+
+/// Makes the glyph at `idx` of the open UFO current. The glyph being left is parked along with its
+/// history, so nothing is lost and nothing is saved. If the glyph can't be read, the one being
+/// left stays current.
+pub fn switch_glyph(v: &mut Editor, i: &mut Interface, idx: usize) -> Result<(), MFEKglifError> {
+    if v.is_modifying() { return Ok(()) }
+    let (current, parked, path) = match v.font.as_ref() {
+        Some(font) => match font.glyphs.get(idx) {
+            Some((_, path)) => (font.current, font.is_parked(idx), path.clone()),
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    if idx == current { return Ok(()) }
+
+    if parked {
+        v.park_glyph();
+        v.unpark_glyph(idx);
+    } else {
+        let glif = read_glif(&path)?;
+        v.park_glyph();
+        v.font.as_mut().unwrap().current = idx;
+        install_glif(v, glif, &path);
+    }
+
+    set_title(i, &path);
+    Ok(())
+}
@@ -4,7 +4,12 @@ use glifparser::{Guideline, GuidelinePoint, IntegerOrFloat};
 
 use crate::editor::Editor;
 
-use std::{process, str};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, process, str};
+
+thread_local! {
+    // Metrics by UFO, so that moving between the glyphs of a font doesn't run MFEKmetadata again.
+    static METRICS: RefCell<HashMap<PathBuf, Vec<String>>> = RefCell::new(HashMap::new());
+}
 
 pub fn fetch_metrics(v: &mut Editor) {
     let (status, qmdbin) = mfek_ipc::module_available("metadata".into());
@@ -14,22 +19,30 @@ pub fn fetch_metrics(v: &mut Editor) {
 
     match &ipc_info.font.as_ref() {
         Some(ref font) => {
-            let command = process::Command::new(qmdbin)
-                .arg(font)
-                .args(&["arbitrary", "-k", "ascender", "-k", "descender"])
-                .output()
-                .expect("No output, font corrupt?");
-
-            let lines_vec = str::from_utf8(&command.stdout).unwrap();
-            let mut lines_iter = lines_vec.lines();
-            let nlines = lines_iter.count();
-            lines_iter = lines_vec.lines();
-
-            if nlines != 2 {
+            let cached = METRICS.with(|m| m.borrow().get(*font).cloned());
+            let lines: Vec<String> = match cached {
+                Some(lines) => lines,
+                None => {
+                    let command = process::Command::new(qmdbin)
+                        .arg(font)
+                        .args(&["arbitrary", "-k", "ascender", "-k", "descender"])
+                        .output()
+                        .expect("No output, font corrupt?");
+
+                    let lines: Vec<String> = str::from_utf8(&command.stdout).unwrap().lines().map(|l| l.to_string()).collect();
+                    // A failed run is tried again next time, in case the font was fixed meanwhile.
+                    if lines.len() == 2 {
+                        METRICS.with(|m| m.borrow_mut().insert(font.to_path_buf(), lines.clone()));
+                    }
+                    lines
+                }
+            };
+
+            if lines.len() != 2 {
                 error!("Cannot set ascender/descender, font corrupt?");
             } else {
                 let names = &["ascender", "descender"];
-                for (i, line) in lines_iter.enumerate() {
+                for (i, line) in lines.iter().enumerate() {
                     v.with_glyph_mut(|glyph|glyph.guidelines.push(Guideline {
                         at: GuidelinePoint {x: 0., y: line.parse().expect("Font is corrupt, metrics not numeric!")},
                         angle: IntegerOrFloat::Float(0.),
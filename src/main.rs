@@ -6,7 +6,7 @@
 use command::{Command, CommandInfo, CommandMod};
 use tools::{EditorEvent, MouseEventType, ToolEnum};
 use editor::{Editor, HandleStyle, PointLabels, PreviewMode, CONSOLE};
-use user_interface::{ImguiManager, Interface};
+use user_interface::{ImguiManager, InputPrompt, Interface};
 use util::argparser::HeadlessMode;
 
 use sdl2::event::{Event, WindowEvent};
@@ -27,6 +27,7 @@ pub mod settings;
 mod system_fonts;
 mod user_interface;
 mod contour_operations;
+mod scripting;
 
 fn main() {
     util::init_env_logger();
@@ -35,19 +36,30 @@ fn main() {
     let args = util::argparser::parse_args();
 
     let mut editor = Editor::new();
+    editor.journal = args.history_journal;
+    editor::history::set_memory_limit(args.history_limit);
 
     if args.headless_mode != HeadlessMode::None {
         editor.headless(&args); // this function always calls exit()
     }
 
-    let filename = filedialog::filename_or_panic(&args.filename, Some("glif"), None);
+    let filename = match filedialog::filename_or_dialog(&args.filename, Some("glif"), None) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(e.exit_code())
+        }
+    };
     let mut interface = Interface::new(filename.to_str().unwrap());
     let mut imgui_manager = ImguiManager::new(&interface.sdl_window);
 
     let mut skulpin_renderer = Interface::initialize_skulpin_renderer(&interface.sdl_window);
 
     // Makes glyph available to on_load_glif events
-    io::load_glif(&mut editor, &mut interface, &filename);
+    if let Err(e) = io::load_glif_or_ufo(&mut editor, &mut interface, &filename) {
+        eprintln!("{}", e);
+        std::process::exit(e.exit_code())
+    }
 
     command::initialize_keybinds();
     tools::console::initialize_console_commands();
@@ -179,6 +191,9 @@ fn main() {
                                 !command_info.command_mod.shift
                             );
                         }
+                        Command::ToggleBooleanPreview => {
+                            editor.set_boolean_preview(!editor.boolean_preview());
+                        }
                         Command::ToggleConsole => {
                             CONSOLE.with(|c| {
                                 c.borrow_mut().active = true;
@@ -198,33 +213,77 @@ fn main() {
                             editor.copy_selection();
                             editor.delete_selection();
                         }
+                        Command::CopyVWSProfile => {
+                            if !editor.copy_vws_profile() {
+                                log::warn!("Select a contour with a variable width stroke to copy its profile");
+                            }
+                        }
+                        Command::PasteVWSProfile => {
+                            if !editor.paste_vws_profile() {
+                                log::warn!("Copy a VWS profile and select contours to paste it onto first");
+                            }
+                        }
                         Command::HistoryUndo => {
                             editor.undo();
                         }
                         Command::HistoryRedo => {
                             editor.redo();
                         }
+                        Command::ToggleHistoryPanel => {
+                            interface.history_panel = !interface.history_panel;
+                        }
                         Command::IOOpen => {
                             let filename = match filedialog::open_filename(Some("glif"), None) {
                                 Some(f) => f,
                                 None => continue,
                             };
-                            io::load_glif(&mut editor, &mut interface, &filename);
+                            if let Err(e) = io::load_glif_or_ufo(&mut editor, &mut interface, &filename) {
+                                interface.report_error(e);
+                            }
+                        }
+                        Command::IOOpenUfo => {
+                            let ufo = match filedialog::open_folder(None) {
+                                Some(f) => f,
+                                None => continue,
+                            };
+                            if let Err(e) = io::load_glif_or_ufo(&mut editor, &mut interface, &ufo) {
+                                interface.report_error(e);
+                            }
                         }
                         Command::IOSave => {
-                            drop(editor.save_glif(false));
+                            if let Err(e) = editor.save_glif() {
+                                interface.report_error(e);
+                            }
                         }
                         Command::IOSaveAs => {
-                            match editor.save_glif(true) {
-                                Ok(pb) => io::load_glif(&mut editor, &mut interface, &pb),
-                                Err(()) => {},
+                            if let Err(e) = io::save_glif_as(&mut editor, &mut interface) {
+                                interface.report_error(e);
                             }
                         }
                         Command::IOFlatten => {
-                            editor.flatten_glif(true);
+                            if let Err(e) = editor.flatten_glif(true) {
+                                interface.report_error(e);
+                            }
                         }
                         Command::IOExport => {
-                            editor.export_glif();
+                            if let Err(e) = editor.export_glif() {
+                                interface.report_error(e);
+                            }
+                        }
+                        Command::NextGlyph | Command::PreviousGlyph => {
+                            let idx = match editor.font.as_ref() {
+                                Some(font) if command_info.command == Command::NextGlyph => font.next(),
+                                Some(font) => font.previous(),
+                                None => continue,
+                            };
+                            if let Err(e) = io::switch_glyph(&mut editor, &mut interface, idx) {
+                                interface.report_error(e);
+                            }
+                        }
+                        Command::GlyphPicker => {
+                            if editor.font.is_some() {
+                                interface.push_prompt(InputPrompt::Glyph);
+                            }
                         }
                         Command::Quit => {
                             break 'main_loop;
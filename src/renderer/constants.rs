@@ -41,6 +41,13 @@ pub static SELECTED_OFFCURVE_STROKE: u32 = 0xff_ffc829;
 
 pub static MEASURE_STROKE: u32 = 0xff_ff5050;
 
+// History panel diff: the older side, then the newer.
+pub static DIFF_BEFORE_STROKE: u32 = 0xcc_e0301e;
+pub static DIFF_AFTER_STROKE: u32 = 0xcc_1e9be0;
+
+// Boolean preview: the outlines of the layers that went into the combined shape.
+pub static OPERAND_STROKE: u32 = 0x88_000000;
+
 pub static LBEARING_STROKE: u32 = 0xff_7d7d7d;
 pub static RBEARING_STROKE: u32 = LBEARING_STROKE;
 
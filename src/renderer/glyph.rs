@@ -29,6 +29,29 @@ pub fn draw_components(v: &Editor, viewport: &Viewport, canvas: &mut Canvas) {
     canvas.draw_path(&path, &paint);
 }
 
+/// Outlines both sides of the history panel's diff over the glyph, each in its own color, so what
+/// moved between them stands out.
+pub fn draw_history_diff(v: &Editor, viewport: &Viewport, canvas: &mut Canvas) {
+    let (before, after) = match v.history_diff_layers() {
+        Some(sides) => sides,
+        None => return,
+    };
+
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(OUTLINE_STROKE_THICKNESS * 1.5 * (1. / viewport.factor));
+
+    for (layers, color) in &[(before, DIFF_BEFORE_STROKE), (after, DIFF_AFTER_STROKE)] {
+        paint.set_color(*color);
+        for layer in layers.iter().filter(|l| l.visible) {
+            let skpaths = layer.outline.to_skia_paths(Some(SkiaPointTransforms{calc_x, calc_y}));
+            if let Some(closed) = skpaths.closed { canvas.draw_path(&closed, &paint); }
+            if let Some(open) = skpaths.open { canvas.draw_path(&open, &paint); }
+        }
+    }
+}
+
 //TODO: pub use crate::events::vws;
 // Before we draw we've got to build a flattened path out of the glyph by resolving
 // each layer operation in turn.
@@ -171,6 +194,13 @@ pub fn draw(canvas: &mut Canvas, v: &mut Editor, viewport: &Viewport, active_lay
         canvas.draw_path(&total_outline_path, &paint);
     }
 
+    cache_and_draw_components(v, viewport, canvas);
+
+    return active_path;    
+}
+
+fn cache_and_draw_components(v: &mut Editor, viewport: &Viewport, canvas: &mut Canvas) {
+    let glif = v.preview.as_mut().unwrap();
     // Cache component rects and flattened outline on MFEKGlif
     match &glif.component_rects {
         Some(_) => {draw_components(v, viewport, canvas);},
@@ -183,6 +213,46 @@ pub fn draw(canvas: &mut Canvas, v: &mut Editor, viewport: &Viewport, active_lay
             }).unwrap_or_else(|e|log::error!("Failed to draw components: {:?}", e));
         },
     }
+}
 
-    return active_path;    
+/// Like `draw`, but fills the glyph as its layer operations resolve, from the editor's cached
+/// `combined_preview`, and outlines the layers that went into it on top so they can still be edited.
+pub fn draw_combined(canvas: &mut Canvas, v: &mut Editor, viewport: &Viewport, active_layer: usize) -> Path {
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_style(PaintStyle::Fill);
+
+    for combined in v.combined_preview.as_ref().unwrap() {
+        match combined.color {
+            Some(color) => { paint.set_color4f(Color4f::new(color[0], color[1], color[2], color[3]), None); },
+            None => { paint.set_color(if viewport.preview_mode == PreviewMode::Paper { PAPER_FILL } else { OUTLINE_FILL }); },
+        }
+        canvas.draw_path(&combined.path, &paint);
+    }
+
+    let mut active_path = Path::new();
+    if viewport.preview_mode != PreviewMode::Paper {
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(OUTLINE_STROKE_THICKNESS * (1. / viewport.factor));
+        paint.set_color(OUTLINE_STROKE);
+        for combined in v.combined_preview.as_ref().unwrap() {
+            canvas.draw_path(&combined.path, &paint);
+        }
+
+        paint.set_color(OPERAND_STROKE);
+        paint.set_stroke_width(OUTLINE_STROKE_THICKNESS * 0.5 * (1. / viewport.factor));
+        for (layer_idx, layer) in v.preview.as_ref().unwrap().layers.iter().enumerate() {
+            if !layer.visible { continue; }
+            let skpaths = layer.outline.to_skia_paths(Some(SkiaPointTransforms{calc_x, calc_y}));
+            if layer_idx == active_layer {
+                active_path = skpaths.clone().into();
+            }
+            if let Some(closed) = skpaths.closed { canvas.draw_path(&closed, &paint); }
+            if let Some(open) = skpaths.open { canvas.draw_path(&open, &paint); }
+        }
+    }
+
+    cache_and_draw_components(v, viewport, canvas);
+
+    active_path
 }
@@ -2,6 +2,7 @@
 
 use crate::{tools::EditorEvent, editor::{PreviewMode, Editor}};
 use crate::user_interface::Interface;
+use crate::user_interface::viewport::Viewport;
 use crate::CONSOLE;
 
 pub mod constants;
@@ -16,6 +17,7 @@ mod anchors;
 mod glyph;
 pub mod viewport;
 pub mod grid;
+pub mod offscreen;
 
 use grid::draw_grid;
 use glifparser::Handle;
@@ -35,7 +37,31 @@ pub enum UIPointType {
 
 pub fn render_frame(v: &mut Editor, i: &mut Interface, canvas: &mut Canvas) {
     canvas.save();
-    let pm = i.viewport.preview_mode;
+    render_glyph(v, &i.viewport, canvas);
+    glyph::draw_history_diff(v, &i.viewport, canvas);
+
+    if i.viewport.preview_mode == PreviewMode::None {
+        v.dispatch_editor_event(i, EditorEvent::Draw {
+            skia_canvas: canvas,
+        });
+    }
+
+    if let Some(grid) = &i.grid {
+        draw_grid(canvas, grid, &i.viewport);
+    }
+    
+    // Reset transformation matrix
+    canvas.restore();
+
+    // Draw console
+    CONSOLE.with(|c| c.borrow_mut().draw(i, canvas));
+}
+
+/// Draws the glyph as the editor shows it through `viewport`, without anything that needs a window:
+/// no tool overlays, grid or console. Leaves the canvas transformed by the viewport's matrix, so
+/// callers should `canvas.save()` first.
+pub fn render_glyph(v: &mut Editor, viewport: &Viewport, canvas: &mut Canvas) {
+    let pm = viewport.preview_mode;
     canvas.clear(if pm == PreviewMode::Paper {
         PAPER_BGCOLOR
     } else {
@@ -43,7 +69,7 @@ pub fn render_frame(v: &mut Editor, i: &mut Interface, canvas: &mut Canvas) {
     });
     // This will change the SkCanvas transformation matrix, and everything from here to
     // canvas.restore() will need to take that matrix into consideration.
-    viewport::redraw_viewport(i, canvas);
+    viewport::redraw_viewport(viewport, canvas);
 
     let dropped = v.with_glyph(|glif| {
         let mut dropped = vec![];
@@ -82,37 +108,28 @@ pub fn render_frame(v: &mut Editor, i: &mut Interface, canvas: &mut Canvas) {
     }
 
     if pm != PreviewMode::Paper || PAPER_DRAW_GUIDELINES {
-        guidelines::draw_all(v, &i.viewport, canvas);
+        guidelines::draw_all(v, viewport, canvas);
     }
 
     let active_layer = v.get_active_layer();
-    let path = glyph::draw(canvas, v, &i.viewport, active_layer);
+    let path = if v.combined_preview.is_some() {
+        glyph::draw_combined(canvas, v, viewport, active_layer)
+    } else {
+        glyph::draw(canvas, v, viewport, active_layer)
+    };
 
     // TODO: let _path = glyph::draw_previews(v, canvas);
 
     match pm {
         PreviewMode::None => {
-            points::draw_all(v, &i.viewport, canvas);
-            points::draw_directions(&i.viewport, path, canvas);
-            anchors::draw_anchors(v, &i.viewport, canvas);
+            points::draw_all(v, viewport, canvas);
+            points::draw_directions(viewport, path, canvas);
+            anchors::draw_anchors(v, viewport, canvas);
             //points::draw_selected(v, canvas);
-            v.dispatch_editor_event(i, EditorEvent::Draw {
-                skia_canvas: canvas,
-            });
         }
         PreviewMode::NoUnselectedPoints => {
             //points::draw_selected(v, canvas);
         }
         PreviewMode::Paper => (),
     }
-
-    if let Some(grid) = &i.grid {
-        draw_grid(canvas, grid, &i.viewport);
-    }
-    
-    // Reset transformation matrix
-    canvas.restore();
-
-    // Draw console
-    CONSOLE.with(|c| c.borrow_mut().draw(i, canvas));
 }
@@ -0,0 +1,143 @@
+//! Rendering without a window, for `--render`. Draws through the same `render_glyph` the editor
+//! uses, onto a raster surface, an SVG canvas or a PDF page.
+
+use super::points::calc::{calc_x, calc_y};
+use super::render_glyph;
+
+use glifparser::outline::skia::{SkiaPointTransforms, ToSkiaPaths};
+use skulpin::skia_safe::{pdf, svg, Canvas, Data, EncodedImageFormat, Rect, Surface};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::editor::Editor;
+use crate::user_interface::viewport::Viewport;
+use crate::util::argparser::RenderArgs;
+use crate::util::error::MFEKglifError;
+use crate::util::messages::{self, Message};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderFormat {
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl RenderFormat {
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let ext = Path::new(filename).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "png" => Some(RenderFormat::Png),
+            "svg" => Some(RenderFormat::Svg),
+            "pdf" => Some(RenderFormat::Pdf),
+            _ => None,
+        }
+    }
+}
+
+/// The part of the canvas, in Skia units (see `calc_x`, `calc_y`), that must be visible: the union
+/// of all visible outlines and the advance width.
+fn glyph_bounds(v: &Editor) -> Rect {
+    let preview = v.preview.as_ref().unwrap();
+    let width = v.with_glyph(|glif| glif.width).unwrap_or(0) as f32;
+    let mut bounds: Option<Rect> = None;
+
+    for layer in preview.layers.iter().filter(|l| l.visible) {
+        let skpaths = layer.outline.to_skia_paths(Some(SkiaPointTransforms{calc_x, calc_y}));
+        for path in skpaths.open.iter().chain(skpaths.closed.iter()) {
+            let b = path.compute_tight_bounds();
+            if b.is_empty() { continue }
+            match bounds {
+                Some(ref mut bounds) => bounds.join(b),
+                None => bounds = Some(b),
+            }
+        }
+    }
+
+    match bounds {
+        Some(b) => Rect::new(b.left.min(calc_x(0.)), b.top, b.right.max(calc_x(width)), b.bottom),
+        // Nothing drawn, so show the em square as the editor would.
+        None => Rect::new(calc_x(0.), calc_y(800.), calc_x(width.max(1.)), calc_y(0.)),
+    }
+}
+
+/// Fits `bounds` into a `size` canvas, centered, leaving `padding` on every side.
+fn fit_viewport(bounds: Rect, args: &RenderArgs) -> Viewport {
+    let (w, h) = (args.size.0 as f32, args.size.1 as f32);
+    let avail_w = (w - args.padding * 2.).max(1.);
+    let avail_h = (h - args.padding * 2.).max(1.);
+    let factor = f32::min(avail_w / bounds.width().max(1.), avail_h / bounds.height().max(1.));
+
+    let offset = (
+        (w - bounds.width() * factor) / 2. - bounds.left * factor,
+        (h - bounds.height() * factor) / 2. - bounds.top * factor,
+    );
+
+    Viewport {
+        winsize: args.size,
+        factor,
+        offset,
+        preview_mode: args.preview_mode,
+        ..Viewport::default()
+    }
+}
+
+fn draw(v: &mut Editor, viewport: &Viewport, canvas: &mut Canvas) {
+    canvas.save();
+    render_glyph(v, viewport, canvas);
+    canvas.restore();
+}
+
+fn render_png(v: &mut Editor, viewport: &Viewport) -> Option<Data> {
+    let mut surface = Surface::new_raster_n32_premul((viewport.winsize.0 as i32, viewport.winsize.1 as i32))?;
+    draw(v, viewport, surface.canvas());
+    surface.image_snapshot().encode_to_data(EncodedImageFormat::PNG)
+}
+
+fn render_svg(v: &mut Editor, viewport: &Viewport) -> Option<Data> {
+    let mut canvas = svg::Canvas::new(Rect::from_wh(viewport.winsize.0 as f32, viewport.winsize.1 as f32), None);
+    draw(v, viewport, &mut canvas);
+    Some(canvas.end())
+}
+
+fn render_pdf(v: &mut Editor, viewport: &Viewport) -> Option<Data> {
+    let mut page = pdf::new_document(None).begin_page((viewport.winsize.0 as f32, viewport.winsize.1 as f32), None);
+    draw(v, viewport, page.canvas());
+    Some(page.end_page().close())
+}
+
+/// Renders the editor's glyph to `args.output`. Layers not in `args.layers` are hidden first; the
+/// editor is headless, so this is never saved.
+pub fn render_to_file(v: &mut Editor, args: &RenderArgs) -> Result<(), MFEKglifError> {
+    let output = PathBuf::from(&args.output);
+
+    if let Some(ref layers) = args.layers {
+        let count = v.get_layer_count();
+        if let Some(&layer) = layers.iter().find(|&&l| l >= count) {
+            return Err(MFEKglifError::LayerOutOfRange { layer, count });
+        }
+        v.with_glyph_mut(|glif| {
+            for (idx, layer) in glif.layers.iter_mut().enumerate() {
+                layer.visible = layers.contains(&idx);
+            }
+        });
+    }
+
+    v.mark_preview_dirty();
+    v.rebuild();
+
+    let viewport = fit_viewport(glyph_bounds(v), args);
+    let data = match args.format {
+        RenderFormat::Png => render_png(v, &viewport),
+        RenderFormat::Svg => render_svg(v, &viewport),
+        RenderFormat::Pdf => render_pdf(v, &viewport),
+    };
+    let data = data.ok_or_else(|| MFEKglifError::Render {
+        path: output.clone(),
+        reason: format!("Skia could not create a {:?} canvas of size {:?}", args.format, args.size),
+    })?;
+
+    fs::write(&output, data.as_bytes()).map_err(|error| MFEKglifError::RenderWrite { path: output.clone(), error })?;
+    messages::emit(Message::Rendered { path: &output });
+    Ok(())
+}
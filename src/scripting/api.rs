@@ -0,0 +1,399 @@
+//! The Editor API as seen from a script. Contour and point functions act on the active layer, just
+//! like tools do. Every change lands in the undo history as one entry per call: layer edits go
+//! through `modify`, glyph-wide ones (width, anchors, guidelines) through `modify_glyph`, and layer
+//! management through the Editor methods the layer list uses, which record their own entries.
+
+use crate::editor::Editor;
+use crate::editor::groups;
+use crate::util::math::ReverseContours as _;
+
+use glifparser::{Anchor, Guideline, GuidelinePoint, Handle, IntegerOrFloat, Point, PointType};
+use glifparser::MFEKGlif;
+use glifparser::glif::{CapType, ContourOperations, InterpolationType, JoinType, LayerOperation, MFEKPointData, VWSContour, VWSHandle};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, FLOAT, INT};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// The `glyph` variable scripts receive.
+#[derive(Clone)]
+pub struct ScriptGlyph {
+    editor: Rc<RefCell<Editor>>,
+}
+
+impl ScriptGlyph {
+    pub fn new(editor: Rc<RefCell<Editor>>) -> Self {
+        Self { editor }
+    }
+
+    fn check_layer(&self, idx: INT) -> ScriptResult<usize> {
+        let count = self.editor.borrow().get_layer_count();
+        if idx < 0 || idx as usize >= count {
+            return Err(format!("Layer {} out of range, glyph has {} layers", idx, count).into());
+        }
+        Ok(idx as usize)
+    }
+
+    fn check_contour(&self, idx: INT) -> ScriptResult<usize> {
+        let count = self.editor.borrow().with_active_layer(|layer| layer.outline.len());
+        if idx < 0 || idx as usize >= count {
+            return Err(format!("Contour {} out of range, layer has {} contours", idx, count).into());
+        }
+        Ok(idx as usize)
+    }
+
+    fn check_point(&self, cidx: INT, pidx: INT) -> ScriptResult<(usize, usize)> {
+        let ci = self.check_contour(cidx)?;
+        let count = self.editor.borrow().with_active_layer(|layer| layer.outline[ci].inner.len());
+        if pidx < 0 || pidx as usize >= count {
+            return Err(format!("Point {} out of range, contour {} has {} points", pidx, ci, count).into());
+        }
+        Ok((ci, pidx as usize))
+    }
+
+    fn check_anchor(&self, idx: INT) -> ScriptResult<usize> {
+        let count = self.editor.borrow().with_glyph(|glif| glif.anchors.len());
+        if idx < 0 || idx as usize >= count {
+            return Err(format!("Anchor {} out of range, glyph has {} anchors", idx, count).into());
+        }
+        Ok(idx as usize)
+    }
+
+    /// Runs `f` on the active layer as a single undoable modification.
+    fn modify<F, R>(&self, description: &str, f: F) -> R
+        where F: FnMut(&mut glifparser::glif::Layer<MFEKPointData>) -> R {
+        let mut v = self.editor.borrow_mut();
+        v.begin_layer_modification(description);
+        let ret = v.with_active_layer_mut(f);
+        v.end_layer_modification();
+        ret
+    }
+
+    /// Runs `f` on the whole glyph as a single undoable modification.
+    fn modify_glyph<F, R>(&self, description: &str, f: F) -> R
+        where F: FnMut(&mut MFEKGlif<MFEKPointData>) -> R {
+        let mut v = self.editor.borrow_mut();
+        v.begin_glyph_modification(description);
+        let ret = v.with_glyph_mut(f);
+        v.end_glyph_modification();
+        ret
+    }
+}
+
+fn dynamic_to_f32(d: &Dynamic) -> Option<f32> {
+    d.as_float().map(|f| f as f32).ok().or_else(|| d.as_int().map(|i| i as f32).ok())
+}
+
+fn map_f32(map: &Map, key: &str) -> Option<f32> {
+    map.get(key).and_then(dynamic_to_f32)
+}
+
+fn handle_to_dynamic(h: Handle) -> Dynamic {
+    match h {
+        Handle::At(x, y) => {
+            let mut m = Map::new();
+            m.insert("x".into(), (x as FLOAT).into());
+            m.insert("y".into(), (y as FLOAT).into());
+            m.into()
+        }
+        Handle::Colocated => Dynamic::UNIT,
+    }
+}
+
+fn dynamic_to_handle(d: Option<&Dynamic>) -> Handle {
+    match d.and_then(|d| d.read_lock::<Map>().map(|m| (map_f32(&m, "x"), map_f32(&m, "y")))) {
+        Some((Some(x), Some(y))) => Handle::At(x, y),
+        _ => Handle::Colocated,
+    }
+}
+
+fn point_to_map(point: &Point<MFEKPointData>) -> Map {
+    let mut m = Map::new();
+    m.insert("x".into(), (point.x as FLOAT).into());
+    m.insert("y".into(), (point.y as FLOAT).into());
+    m.insert("a".into(), handle_to_dynamic(point.a));
+    m.insert("b".into(), handle_to_dynamic(point.b));
+    m.insert("type".into(), format!("{:?}", point.ptype).into());
+    m.insert("name".into(), point.name.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT));
+    m
+}
+
+fn map_to_point(map: &Map, ptype: PointType) -> ScriptResult<Point<MFEKPointData>> {
+    let (x, y) = match (map_f32(map, "x"), map_f32(map, "y")) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Err("Points must be maps with numeric x and y".into()),
+    };
+    let mut point = Point::from_x_y_type((x, y), ptype);
+    point.a = dynamic_to_handle(map.get("a"));
+    point.b = dynamic_to_handle(map.get("b"));
+    if point.a != Handle::Colocated || point.b != Handle::Colocated {
+        if ptype != PointType::Move { point.ptype = PointType::Curve; }
+    }
+    Ok(point)
+}
+
+fn str_to_layer_operation(s: &str) -> ScriptResult<Option<LayerOperation>> {
+    Ok(match s.to_lowercase().as_str() {
+        "" | "none" | "combine" => None,
+        "difference" => Some(LayerOperation::Difference),
+        "union" => Some(LayerOperation::Union),
+        "intersect" => Some(LayerOperation::Intersect),
+        "xor" => Some(LayerOperation::XOR),
+        _ => return Err(format!("Unknown layer operation {:?}", s).into()),
+    })
+}
+
+fn str_to_cap_type(s: &str) -> ScriptResult<CapType> {
+    Ok(match s.to_lowercase().as_str() {
+        "round" => CapType::Round,
+        "square" => CapType::Square,
+        "circle" => CapType::Circle,
+        _ => return Err(format!("Unknown cap type {:?}", s).into()),
+    })
+}
+
+fn str_to_join_type(s: &str) -> ScriptResult<JoinType> {
+    Ok(match s.to_lowercase().as_str() {
+        "round" => JoinType::Round,
+        "miter" => JoinType::Miter,
+        "bevel" => JoinType::Bevel,
+        "circle" => JoinType::Circle,
+        _ => return Err(format!("Unknown join type {:?}", s).into()),
+    })
+}
+
+impl ScriptGlyph {
+    fn get_vws_contour(&self, ci: usize) -> VWSContour {
+        let (op, len) = self.editor.borrow().with_active_layer(|layer| (layer.outline[ci].operation.clone(), layer.outline[ci].inner.len()));
+        match op {
+            Some(ContourOperations::VariableWidthStroke { data }) => data,
+            _ => VWSContour {
+                // Same shape as the VWS tool generates: one handle per point, plus one for the
+                // imaginary point closing the contour.
+                handles: vec![VWSHandle {
+                    left_offset: 10.,
+                    right_offset: 10.,
+                    interpolation: InterpolationType::Linear,
+                    tangent_offset: 0.,
+                }; len + 1],
+                cap_start_type: CapType::Round,
+                cap_end_type: CapType::Round,
+                join_type: JoinType::Round,
+                remove_internal: false,
+                remove_external: false,
+            },
+        }
+    }
+
+    fn set_vws_contour(&self, ci: usize, description: &str, data: VWSContour) {
+        self.modify(description, |layer| {
+            layer.outline[ci].operation = Some(ContourOperations::VariableWidthStroke { data: data.clone() });
+        });
+    }
+}
+
+pub fn register(engine: &mut Engine) {
+    engine.register_type_with_name::<ScriptGlyph>("Glyph");
+
+    // Glyph metadata
+    engine.register_get("name", |g: &mut ScriptGlyph| g.editor.borrow().with_glyph(|glif| glif.name.clone()));
+    engine.register_get("width", |g: &mut ScriptGlyph| -> Dynamic {
+        g.editor.borrow().with_glyph(|glif| glif.width).map(|w| Dynamic::from(w as INT)).unwrap_or(Dynamic::UNIT)
+    });
+    engine.register_set("width", |g: &mut ScriptGlyph, w: INT| -> ScriptResult<()> {
+        if w < 0 { return Err("Glyph width cannot be negative".into()) }
+        g.modify_glyph("Set width.", |glif| glif.width = Some(w as u64));
+        Ok(())
+    });
+
+    // Layers
+    engine.register_fn("layer_count", |g: &mut ScriptGlyph| g.editor.borrow().get_layer_count() as INT);
+    engine.register_get("active_layer", |g: &mut ScriptGlyph| g.editor.borrow().get_active_layer() as INT);
+    engine.register_set("active_layer", |g: &mut ScriptGlyph, idx: INT| -> ScriptResult<()> {
+        let idx = g.check_layer(idx)?;
+        g.editor.borrow_mut().set_active_layer(idx);
+        Ok(())
+    });
+    engine.register_fn("new_layer", |g: &mut ScriptGlyph| {
+        let mut v = g.editor.borrow_mut();
+        v.new_layer();
+        v.get_active_layer() as INT
+    });
+    engine.register_fn("delete_layer", |g: &mut ScriptGlyph, idx: INT| -> ScriptResult<()> {
+        let idx = g.check_layer(idx)?;
+        g.editor.borrow_mut().delete_layer(idx, true);
+        Ok(())
+    });
+    engine.register_fn("layer_name", |g: &mut ScriptGlyph, idx: INT| -> ScriptResult<String> {
+        let idx = g.check_layer(idx)?;
+        Ok(g.editor.borrow().with_glyph(|glif| groups::display_name(&glif.layers[idx]).to_string()))
+    });
+    engine.register_fn("set_layer_name", |g: &mut ScriptGlyph, name: &str| {
+        let mut v = g.editor.borrow_mut();
+        let idx = v.get_active_layer();
+        v.rename_layer(idx, name);
+    });
+    engine.register_fn("set_layer_visible", |g: &mut ScriptGlyph, visible: bool| {
+        g.modify("Toggled layer visibility.", |layer| layer.visible = visible);
+    });
+    engine.register_fn("set_layer_operation", |g: &mut ScriptGlyph, op: &str| -> ScriptResult<()> {
+        let op = str_to_layer_operation(op)?;
+        g.modify("Changed layer operation.", |layer| layer.operation = op.clone());
+        Ok(())
+    });
+
+    // Contours and points
+    engine.register_fn("contour_count", |g: &mut ScriptGlyph| g.editor.borrow().with_active_layer(|layer| layer.outline.len()) as INT);
+    engine.register_fn("point_count", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<INT> {
+        let ci = g.check_contour(ci)?;
+        Ok(g.editor.borrow().with_active_layer(|layer| layer.outline[ci].inner.len()) as INT)
+    });
+    engine.register_fn("is_closed", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<bool> {
+        let ci = g.check_contour(ci)?;
+        Ok(g.editor.borrow().with_active_layer(|layer| layer.outline[ci].inner.first().map(|p| p.ptype != PointType::Move).unwrap_or(false)))
+    });
+    engine.register_fn("get_point", |g: &mut ScriptGlyph, ci: INT, pi: INT| -> ScriptResult<Map> {
+        let (ci, pi) = g.check_point(ci, pi)?;
+        Ok(g.editor.borrow().with_active_layer(|layer| point_to_map(&layer.outline[ci].inner[pi])))
+    });
+    engine.register_fn("move_point", |g: &mut ScriptGlyph, ci: INT, pi: INT, x: FLOAT, y: FLOAT| -> ScriptResult<()> {
+        let (ci, pi) = g.check_point(ci, pi)?;
+        g.modify("Move point.", |layer| crate::editor::util::move_point(&mut layer.outline, ci, pi, x as f32, y as f32));
+        Ok(())
+    });
+    engine.register_fn("add_contour", |g: &mut ScriptGlyph, points: Array, closed: bool| -> ScriptResult<INT> {
+        if points.is_empty() { return Err("Cannot add a contour without points".into()) }
+        let mut contour = Vec::with_capacity(points.len());
+        for (i, p) in points.iter().enumerate() {
+            let map = p.read_lock::<Map>().ok_or("Points must be maps with numeric x and y")?;
+            let ptype = if i == 0 && !closed { PointType::Move } else { PointType::Line };
+            contour.push(map_to_point(&map, ptype)?);
+        }
+        Ok(g.modify("Add contour.", |layer| {
+            layer.outline.push(contour.clone().into());
+            layer.outline.len() - 1
+        }) as INT)
+    });
+    engine.register_fn("delete_contour", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        g.modify("Delete contour.", |layer| { layer.outline.remove(ci); });
+        Ok(())
+    });
+    engine.register_fn("reverse_contour", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        g.modify("Reversing contours...", |layer| {
+            layer.outline[ci].inner = layer.outline[ci].inner.clone().reverse_contours();
+        });
+        Ok(())
+    });
+
+    // Contour operations
+    engine.register_fn("operation", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<String> {
+        let ci = g.check_contour(ci)?;
+        Ok(match g.editor.borrow().with_active_layer(|layer| layer.outline[ci].operation.clone()) {
+            Some(ContourOperations::VariableWidthStroke { .. }) => "vws",
+            Some(ContourOperations::PatternAlongPath { .. }) => "pap",
+            None => "",
+        }.to_string())
+    });
+    engine.register_fn("clear_operation", |g: &mut ScriptGlyph, ci: INT| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        g.modify("Removed contour operation.", |layer| layer.outline[ci].operation = None);
+        Ok(())
+    });
+    engine.register_fn("set_vws_width", |g: &mut ScriptGlyph, ci: INT, left: FLOAT, right: FLOAT| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        let mut data = g.get_vws_contour(ci);
+        for handle in data.handles.iter_mut() {
+            handle.left_offset = left;
+            handle.right_offset = right;
+        }
+        g.set_vws_contour(ci, "Set VWS width.", data);
+        Ok(())
+    });
+    engine.register_fn("set_vws_handle", |g: &mut ScriptGlyph, ci: INT, hi: INT, left: FLOAT, right: FLOAT, tangent: FLOAT| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        let mut data = g.get_vws_contour(ci);
+        if hi < 0 || hi as usize >= data.handles.len() {
+            return Err(format!("VWS handle {} out of range, contour {} has {} handles", hi, ci, data.handles.len()).into());
+        }
+        let handle = &mut data.handles[hi as usize];
+        handle.left_offset = left;
+        handle.right_offset = right;
+        handle.tangent_offset = tangent;
+        g.set_vws_contour(ci, "Move VWS handle.", data);
+        Ok(())
+    });
+    engine.register_fn("set_vws_caps", |g: &mut ScriptGlyph, ci: INT, start: &str, end: &str| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        let mut data = g.get_vws_contour(ci);
+        data.cap_start_type = str_to_cap_type(start)?;
+        data.cap_end_type = str_to_cap_type(end)?;
+        g.set_vws_contour(ci, "VWS dialog modification.", data);
+        Ok(())
+    });
+    engine.register_fn("set_vws_join", |g: &mut ScriptGlyph, ci: INT, join: &str| -> ScriptResult<()> {
+        let ci = g.check_contour(ci)?;
+        let mut data = g.get_vws_contour(ci);
+        data.join_type = str_to_join_type(join)?;
+        g.set_vws_contour(ci, "VWS dialog modification.", data);
+        Ok(())
+    });
+
+    // Anchors
+    engine.register_fn("anchors", |g: &mut ScriptGlyph| -> Array {
+        g.editor.borrow().with_glyph(|glif| glif.anchors.iter().map(|a| {
+            let mut m = Map::new();
+            m.insert("name".into(), a.class.clone().into());
+            m.insert("x".into(), (a.x as FLOAT).into());
+            m.insert("y".into(), (a.y as FLOAT).into());
+            Dynamic::from(m)
+        }).collect())
+    });
+    engine.register_fn("add_anchor", |g: &mut ScriptGlyph, name: &str, x: FLOAT, y: FLOAT| {
+        g.modify_glyph("Added anchor.", |glif| {
+            let mut anchor = Anchor::new();
+            anchor.class = name.to_string();
+            anchor.x = x as f32;
+            anchor.y = y as f32;
+            glif.anchors.push(anchor);
+        });
+    });
+    engine.register_fn("remove_anchor", |g: &mut ScriptGlyph, idx: INT| -> ScriptResult<()> {
+        let idx = g.check_anchor(idx)?;
+        g.modify_glyph("Removed anchor.", |glif| { glif.anchors.remove(idx); });
+        Ok(())
+    });
+
+    // Guidelines
+    engine.register_fn("guidelines", |g: &mut ScriptGlyph| -> Array {
+        g.editor.borrow().with_glyph(|glif| glif.guidelines.iter().map(|gl| {
+            let mut m = Map::new();
+            m.insert("x".into(), (gl.at.x as FLOAT).into());
+            m.insert("y".into(), (gl.at.y as FLOAT).into());
+            m.insert("angle".into(), (f32::from(gl.angle) as FLOAT).into());
+            m.insert("name".into(), gl.name.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT));
+            Dynamic::from(m)
+        }).collect())
+    });
+    engine.register_fn("add_guideline", |g: &mut ScriptGlyph, x: FLOAT, y: FLOAT, angle: FLOAT, name: &str| {
+        g.modify_glyph("Added guideline.", |glif| glif.guidelines.push(Guideline {
+            at: GuidelinePoint { x: x as f32, y: y as f32 },
+            angle: IntegerOrFloat::Float(angle as f32),
+            name: if name.is_empty() { None } else { Some(name.to_string()) },
+            color: None,
+            identifier: None,
+        }));
+    });
+
+    // History
+    engine.register_fn("undo", |g: &mut ScriptGlyph| g.editor.borrow_mut().undo());
+    engine.register_fn("redo", |g: &mut ScriptGlyph| g.editor.borrow_mut().redo());
+
+    // I/O
+    engine.register_fn("save", |g: &mut ScriptGlyph| -> ScriptResult<()> { g.editor.borrow_mut().save_glif().map(drop).map_err(|e| e.to_string().into()) });
+    engine.register_fn("flatten", |g: &mut ScriptGlyph| -> ScriptResult<()> { g.editor.borrow_mut().flatten_glif(false).map_err(|e| e.to_string().into()) });
+    engine.register_fn("export", |g: &mut ScriptGlyph| -> ScriptResult<()> { g.editor.borrow_mut().export_glif().map_err(|e| e.to_string().into()) });
+}
@@ -0,0 +1,67 @@
+//! Headless scripting. Scripts are written in Rhai, a small embedded language which has no access
+//! to the filesystem, network or processes unless we give it to them. The only thing a script can
+//! touch is the glyph it was given, through the `glyph` variable, and the functions in `api.rs`.
+
+use crate::editor::Editor;
+
+use rhai::{Engine, EvalAltResult, Scope};
+use rhai::module_resolvers::DummyModuleResolver;
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+mod api;
+pub use self::api::ScriptGlyph;
+
+// Limits so a runaway script in CI fails instead of hanging or eating all memory.
+const MAX_OPERATIONS: u64 = 50_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1 << 20;
+const MAX_ARRAY_SIZE: usize = 1 << 20;
+const MAX_MAP_SIZE: usize = 1 << 16;
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+
+    // `import` could otherwise read arbitrary .rhai files off the disk.
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_MAP_SIZE);
+
+    engine.on_print(|s| log::info!("[script] {}", s));
+    engine.on_debug(|s, _src, pos| log::debug!("[script] {:?}: {}", pos, s));
+
+    api::register(&mut engine);
+
+    engine
+}
+
+/// Runs the script at `script` against the editor's loaded glyph. The editor is lent to the script
+/// for its duration and handed back afterwards, whether the script succeeded or not.
+pub fn run_script<F: AsRef<Path>>(v: &mut Editor, script: F) -> Result<(), Box<EvalAltResult>> {
+    let engine = sandboxed_engine();
+
+    let shared = Rc::new(RefCell::new(std::mem::replace(v, Editor::new())));
+    let result = {
+        let mut scope = Scope::new();
+        scope.push("glyph", ScriptGlyph::new(shared.clone()));
+        engine.run_file_with_scope(&mut scope, script.as_ref().to_path_buf())
+    };
+
+    // The scope, and with it every handle the script could have stashed away, is gone now.
+    *v = match Rc::try_unwrap(shared) {
+        Ok(editor) => editor.into_inner(),
+        Err(_) => unreachable!("Script outlived its scope!"),
+    };
+    v.end_layer_modification();
+
+    result
+}
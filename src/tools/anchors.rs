@@ -10,12 +10,15 @@ use imgui;
 pub struct Anchors {
     /// Selected anchor
     anchor_idx: Option<usize>,
+    /// Whether the mouse went down on the selected anchor and may drag it
+    dragging: bool,
 }
 
 impl Anchors {
     pub fn new() -> Self {
         Anchors {
             anchor_idx: None,
+            dragging: false,
         }
     }
 }
@@ -27,7 +30,7 @@ impl Tool for Anchors {
                 match event_type {
                     MouseEventType::Moved => { self.mouse_moved(v, meta) }
                     MouseEventType::Pressed => { self.mouse_pressed(v, i, meta) }
-                    //MouseEventType::Released => { self.mouse_released(v, meta) }
+                    MouseEventType::Released => { self.mouse_released(v) }
                     _ => {}
                 }
             },
@@ -50,10 +53,12 @@ impl Anchors {
         if let Some(idx) = self.anchor_idx {
             let _scale = i.viewport.factor;
             v.with_glyph(|glif| {
-                let anchor = &glif.anchors[idx];
-                let mut paint = Paint::default();
-                paint.set_color(SELECTED_ANCHOR_COLOR);
-                canvas.draw_circle((calc_x(anchor.x), calc_y(anchor.y)), 2.5, &paint);
+                // Undo may have taken the anchor away.
+                if let Some(anchor) = glif.anchors.get(idx) {
+                    let mut paint = Paint::default();
+                    paint.set_color(SELECTED_ANCHOR_COLOR);
+                    canvas.draw_circle((calc_x(anchor.x), calc_y(anchor.y)), 2.5, &paint);
+                }
             });
         }
     }
@@ -62,6 +67,9 @@ impl Anchors {
 // Make dialog box at right
 impl Anchors {
     fn anchor_settings(&mut self, v: &mut Editor, i: &Interface, ui: &imgui::Ui) {
+        if self.anchor_idx.map(|idx| idx >= v.with_glyph(|glif| glif.anchors.len())).unwrap_or(false) {
+            self.anchor_idx = None;
+        }
         let (tx, ty, tw, th) = i.get_tools_dialog_rect();
         imgui::Window::new(imgui::im_str!("Anchor Settings"))
             .bg_alpha(1.) // See comment on fn redraw_skia
@@ -80,66 +88,75 @@ impl Anchors {
             )
             .build(ui, || {
                 if let Some(idx) = self.anchor_idx {
-                    v.with_glyph_mut(|glif| {
-                        // X
-                        let mut x = imgui::im_str!("{}", glif.anchors[idx].x);
-                        let entered;
-                        {
-                        let it = ui.input_text(imgui::im_str!("X"), &mut x);
-                        entered = it.enter_returns_true(true)
-                            .chars_decimal(true)
-                            .chars_noblank(true)
-                            .auto_select_all(true)
-                            .build();
-                        }
-                        if entered {
-                            if x.to_str().len() > 0 {
-                                let new_x: f32 = x.to_str().parse().unwrap();
-                                glif.anchors[idx].x = new_x;
-                            }
+                    let (x, y, class) = v.with_glyph(|glif| (glif.anchors[idx].x, glif.anchors[idx].y, glif.anchors[idx].class.clone()));
+                    // X
+                    let mut x = imgui::im_str!("{}", x);
+                    let entered;
+                    {
+                    let it = ui.input_text(imgui::im_str!("X"), &mut x);
+                    entered = it.enter_returns_true(true)
+                        .chars_decimal(true)
+                        .chars_noblank(true)
+                        .auto_select_all(true)
+                        .build();
+                    }
+                    if entered {
+                        if let Ok(new_x) = x.to_str().parse::<f32>() {
+                            modify_glyph(v, "Moved anchor.", |glif| glif.anchors[idx].x = new_x);
                         }
-                        // Y
-                        let mut y = imgui::im_str!("{}", glif.anchors[idx].y);
-                        let entered;
-                        {
-                        let it = ui.input_text(imgui::im_str!("Y"), &mut y);
-                        entered = it.enter_returns_true(true)
-                            .chars_decimal(true)
-                            .chars_noblank(true)
-                            .auto_select_all(true)
-                            .build();
+                    }
+                    // Y
+                    let mut y = imgui::im_str!("{}", y);
+                    let entered;
+                    {
+                    let it = ui.input_text(imgui::im_str!("Y"), &mut y);
+                    entered = it.enter_returns_true(true)
+                        .chars_decimal(true)
+                        .chars_noblank(true)
+                        .auto_select_all(true)
+                        .build();
+                    }
+                    if entered {
+                        if let Ok(new_y) = y.to_str().parse::<f32>() {
+                            modify_glyph(v, "Moved anchor.", |glif| glif.anchors[idx].y = new_y);
                         }
-                        if entered {
-                            if y.to_str().len() > 0 {
-                                let new_y: f32 = y.to_str().parse().unwrap();
-                                glif.anchors[idx].y = new_y;
-                            }
+                    }
+                    // Class
+                    let mut class = imgui::im_str!("{}", &class);
+                    let entered;
+                    {
+                    let it = ui.input_text(imgui::im_str!("Class"), &mut class);
+                    entered = it.enter_returns_true(true)
+                        .chars_noblank(true)
+                        .auto_select_all(true)
+                        .build();
+                    }
+                    if entered {
+                        if class.to_str().len() > 0 {
+                            let new_class = class.to_str().to_string();
+                            modify_glyph(v, "Renamed anchor.", |glif| glif.anchors[idx].class = new_class.clone());
                         }
-                        // Class
-                        let mut class = imgui::im_str!("{}", &glif.anchors[idx].class);
-                        let entered;
-                        {
-                        let it = ui.input_text(imgui::im_str!("Class"), &mut class);
-                        entered = it.enter_returns_true(true)
-                            .chars_noblank(true)
-                            .auto_select_all(true)
-                            .build();
-                        }
-                        if entered {
-                            if class.to_str().len() > 0 {
-                                glif.anchors[idx].class = class.to_str().to_string();
-                            }
-                        }
-                    });
+                    }
                 }
             });
     }
 }
 
+/// Makes one undoable change to the glyph's anchors, unless a drag is already changing them.
+fn modify_glyph<F>(v: &mut Editor, description: &str, f: F)
+    where F: FnMut(&mut MFEKGlif<MFEKPointData>) {
+    if v.is_modifying() { return }
+    v.begin_glyph_modification(description);
+    v.with_glyph_mut(f);
+    v.end_glyph_modification();
+}
+
 // Mouse
+use std::mem;
 use std::rc::Rc;
 use crate::editor::{Editor};
 use glifparser::Anchor as GlifAnchor;
+use glifparser::MFEKGlif;
 use skulpin::skia_safe::{Paint};
 impl Anchors {
     fn mouse_pressed(&mut self, v: &mut Editor, i: &mut Interface, meta: MouseInfo) {
@@ -164,15 +181,18 @@ impl Anchors {
             }
         });
 
-        // If we have, return, and wait for motion
-        if let Some(_idx) = self.anchor_idx { return }
+        // If we have, return, and wait for motion.
+        if self.anchor_idx.is_some() {
+            self.dragging = true;
+            return
+        }
 
         let position = i.mouse_info.position;
         i.push_prompt(InputPrompt::Text {
             label: "Anchor name:".to_string(),
             default: "".to_string(),
             func: Rc::new(move |v, string| {
-                v.with_glyph_mut(|glif| {
+                modify_glyph(v, "Added anchor.", |glif| {
                     let mut anchor = GlifAnchor::new();
                     anchor.x = f32::floor(calc_x(position.0));
                     anchor.y = f32::floor(calc_y(position.1));
@@ -184,23 +204,32 @@ impl Anchors {
     }
 
     fn mouse_moved(&mut self, v: &mut Editor, meta: MouseInfo) {
-        if !meta.is_down { return }
+        let idx = match self.anchor_idx {
+            Some(idx) if meta.is_down && self.dragging => idx,
+            _ => return,
+        };
 
+        // The move is undone as one, from the first motion to release.
+        if !v.is_modifying() { v.begin_glyph_modification("Moved anchor.") }
         v.with_glyph_mut(|glif| {
-            if let Some(idx) = self.anchor_idx {
-                // Anchors can't be non-integers in OT spec
-                glif.anchors[idx].x = f32::floor(calc_x(meta.position.0));
-                glif.anchors[idx].y = f32::floor(calc_y(meta.position.1));
-            }
+            // Anchors can't be non-integers in OT spec
+            glif.anchors[idx].x = f32::floor(calc_x(meta.position.0));
+            glif.anchors[idx].y = f32::floor(calc_y(meta.position.1));
         });
     }
+
+    fn mouse_released(&mut self, v: &mut Editor) {
+        if mem::take(&mut self.dragging) && v.is_modifying() {
+            v.end_glyph_modification();
+        }
+    }
 }
 
 // Keyed
 impl Anchors {
     fn delete_selection(&mut self, v: &mut Editor) {
         if let Some(idx) = self.anchor_idx {
-            v.with_glyph_mut(|glif| {
+            modify_glyph(v, "Deleted anchor.", |glif| {
                 glif.anchors.remove(idx);
             });
         }
@@ -1,5 +1,6 @@
 use crate::editor::Editor;
-use crate::user_interface::Interface;
+use crate::user_interface::{InputPrompt, Interface};
+use skulpin::skia_safe::PathOp;
 use std::{cell::RefCell, collections::HashMap};
 
 type Callback = Box<(dyn Fn(&mut Editor, &mut Interface, Vec<String>) -> () + 'static)>;
@@ -27,6 +28,85 @@ pub fn initialize_console_commands() {
             }
         })));
     
+        h.borrow_mut().insert("check", ("Check the glyph for problems", callback(|v, i, _s| {
+            let findings = v.check();
+            let message = if findings.is_empty() {
+                "No problems found.".to_string()
+            } else {
+                findings.iter().map(|f| format!("{}: {} ({})", f.severity, f.message, f.location)).collect::<Vec<_>>().join("\n")
+            };
+            i.push_prompt(InputPrompt::Message { title: "Check".to_string(), message });
+        })));
+
+        for &(name, help, op) in &[
+            ("union", "Unite the selected contours", PathOp::Union),
+            ("subtract", "Cut the first selected contour with the others", PathOp::Difference),
+            ("intersect", "Keep where the selected contours overlap", PathOp::Intersect),
+            ("xor", "Keep where an odd number of selected contours overlap", PathOp::XOR),
+        ] {
+            h.borrow_mut().insert(name, (help, callback(move |v, _i, _s| {
+                if !v.contour_boolean(op) {
+                    log::warn!("Select points on two or more closed contours first");
+                }
+            })));
+        }
+
+        h.borrow_mut().insert("apply", ("Replace contour operations with their outlines", callback(|v, _i, _s| {
+            if !v.apply_contour_operations() {
+                log::warn!("No contour with an operation to apply");
+            }
+        })));
+
+        h.borrow_mut().insert("removeoverlap", ("Unite the overlapping contours of the layer", callback(|v, _i, _s| {
+            v.remove_overlap();
+        })));
+
+        h.borrow_mut().insert("vwscopy", ("Copy the VWS profile of the contour", callback(|v, _i, _s| {
+            if !v.copy_vws_profile() {
+                log::warn!("Select a contour with a variable width stroke first");
+            }
+        })));
+
+        h.borrow_mut().insert("vwspaste", ("Stroke the selected contours with the copied VWS profile", callback(|v, _i, _s| {
+            if !v.paste_vws_profile() {
+                log::warn!("Copy a VWS profile and select contours to paste it onto first");
+            }
+        })));
+
+        h.borrow_mut().insert("vwssave", ("Save the copied VWS profile to the library by name", callback(|v, i, s| {
+            if s.len() != 1 || v.vws_profile.is_none() { return; } // FIXME: Tell user about errors!
+            if let Err(e) = v.save_vws_profile(&s[0]) {
+                i.report_error(e);
+            }
+        })));
+
+        h.borrow_mut().insert("vwsload", ("Copy a VWS profile from the library by name", callback(|v, i, s| {
+            if s.len() != 1 { return; } // FIXME: Tell user about errors!
+            match v.load_vws_profile(&s[0]) {
+                Ok(true) => {}
+                Ok(false) => log::warn!("No VWS profile named {:?} in the library", &s[0]),
+                Err(e) => i.report_error(e),
+            }
+        })));
+
+        h.borrow_mut().insert("vwsdelete", ("Remove a VWS profile from the library by name", callback(|v, i, s| {
+            if s.len() != 1 { return; } // FIXME: Tell user about errors!
+            match v.delete_vws_profile(&s[0]) {
+                Ok(true) => {}
+                Ok(false) => log::warn!("No VWS profile named {:?} in the library", &s[0]),
+                Err(e) => i.report_error(e),
+            }
+        })));
+
+        h.borrow_mut().insert("vwsprofiles", ("List the VWS profiles in the library", callback(|v, i, _s| {
+            let names = match v.vws_profile_names() {
+                Ok(names) => names,
+                Err(e) => return i.report_error(e),
+            };
+            let message = if names.is_empty() { "The library is empty.".to_string() } else { names.join("\n") };
+            i.push_prompt(InputPrompt::Message { title: "VWS profiles".to_string(), message });
+        })));
+
         h.borrow_mut().insert("q", ("Quit", callback(|v, _i, _s| {
             v.quit_requested = true;
         })));
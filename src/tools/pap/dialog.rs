@@ -2,26 +2,14 @@ use super::super::prelude::*;
 use super::PAP;
 use imgui::Ui;
 use crate::user_interface::Interface;
-use glifparser::glif::{ContourOperations, PatternCopies, PatternSubdivide};
-
-fn imgui_decimal_text_field(label: &str, ui: &imgui::Ui, data: &mut f32) {
-    let mut x = imgui::im_str!("{}", data);
-    let label = imgui::ImString::new(label);
-    let entered;
-    {
-    let it = ui.input_text(&label, &mut x);
-    entered = it.enter_returns_true(true)
-        .chars_decimal(true)
-        .chars_noblank(true)
-        .auto_select_all(true)
+use glifparser::glif::{ContourOperations, PAPContour, PatternCopies, PatternSubdivide};
+
+fn float_field(label: &imgui::ImStr, ui: &Ui, data: f64) -> Option<f64> {
+    let mut new_data = data as f32;
+    let entered = ui.input_float(label, &mut new_data)
+        .enter_returns_true(true)
         .build();
-    }
-    if entered {
-        if x.to_str().len() > 0 {
-            let new_x: f32 = x.to_str().parse().unwrap();
-            *data = new_x;
-        }
-    }
+    if entered && new_data as f64 != data { Some(new_data as f64) } else { None }
 }
 
 fn repeat_type_to_idx(rt: PatternCopies) -> usize {
@@ -40,6 +28,14 @@ fn idx_to_repeat_type(idx: usize) -> PatternCopies {
     }
 }
 
+fn set_pap_contour(v: &mut Editor, contour_idx: usize, data: PAPContour) {
+    let new_op = ContourOperations::PatternAlongPath { data };
+
+    v.begin_layer_modification("PAP dialog modification.");
+    v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
+    v.end_layer_modification();
+}
+
 impl PAP {
     pub fn tool_dialog(&mut self, v: &mut Editor, i: &Interface, ui: &Ui) {
         let (tx, ty, tw, th) = i.get_tools_dialog_rect();
@@ -65,162 +61,98 @@ impl PAP {
 
             let operation = v.with_active_layer(|layer| layer.outline[contour_idx].operation.clone() );
 
-            match operation {
-                Some(ContourOperations::PatternAlongPath { data }) => {
-                    let old_repeat = repeat_type_to_idx(data.copies.clone());
-                    let mut new_repeat = old_repeat;
-            
-                    let options = [
-                        imgui::im_str!("Single"),
-                        imgui::im_str!("Repeated"),
-                    ];
-            
-                    imgui::ComboBox::new(imgui::im_str!("Mode")).build_simple_string(
-                        ui,
-                        &mut new_repeat,
-                        &options,
-                    );
-            
-                    let repeat_selection = idx_to_repeat_type(new_repeat);
-            
-                    // we only update the contour and previews when our selection changes
-                    if old_repeat != new_repeat {
-                        let mut new_data = data.clone();
-                        new_data.copies = repeat_selection;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let options = [
-                        imgui::im_str!("0"),
-                        imgui::im_str!("1"),
-                        imgui::im_str!("2"),
-                        imgui::im_str!("3"),
-                    ];
-
-                    let cur_subdivisions = match data.subdivide {
-                        PatternSubdivide::Simple (times) => {
-                            times
-                        }
-                        _ => 0
-                    };
-
-                    let mut new_subdivisions = cur_subdivisions;
-
-                    imgui::ComboBox::new(imgui::im_str!("Subdivisions")).build_simple_string(
-                        ui,
-                        &mut new_subdivisions,
-                        &options,
-                    );
-
-                    if cur_subdivisions != new_subdivisions {
-                        let mut new_data = data.clone();
-                        new_data.subdivide = if new_subdivisions == 0 { PatternSubdivide::Off } else { PatternSubdivide::Simple(new_subdivisions) };
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let mut new_center = data.center_pattern;
-                    ui.checkbox(imgui::im_str!("Center"), &mut new_center);
-                    if new_center != data.center_pattern {
-                        let mut new_data = data.clone();
-                        new_data.center_pattern = new_center;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let old_stretch = data.stretch;
-                    let mut new_stretch = old_stretch;
-                    ui.checkbox(imgui::im_str!("Stretch"), &mut new_stretch);
-                    if old_stretch != new_stretch {
-                        let mut new_data = data.clone();
-                        new_data.stretch = new_stretch;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let old_simplify = data.simplify;
-                    let mut new_simplify = old_simplify;
-                    ui.checkbox(imgui::im_str!("Simplify"), &mut new_simplify);
-                    if old_simplify != new_simplify {
-                        let mut new_data = data.clone();
-                        new_data.simplify = new_simplify;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let mut new_spacing = data.spacing as f32;
-                    imgui_decimal_text_field("Spacing", ui, &mut new_spacing);
-
-                    if new_spacing != data.spacing as f32 {
-                        let mut new_data = data.clone();
-                        new_data.spacing = new_spacing as f64;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let mut new_normal = data.normal_offset as f32;
-                    imgui_decimal_text_field("Normal Offset", ui, &mut new_normal);
-
-                    if new_normal != data.normal_offset as f32 {
-                        let mut new_data = data.clone();
-                        new_data.normal_offset = new_normal as f64;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let mut new_tangent = data.tangent_offset as f32;
-                    imgui_decimal_text_field("Tangent Offset", ui, &mut new_tangent);
-
-                    if new_tangent != data.tangent_offset as f32 {
-                        let mut new_data = data.clone();
-                        new_data.tangent_offset = new_tangent as f64;
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
-
-                    let mut new_x_scale = data.pattern_scale.0 as f32;
-                    let mut new_y_scale = data.pattern_scale.1 as f32;
-                    imgui_decimal_text_field("##", ui, &mut new_x_scale);
-                    ui.same_line(0.);
-                    imgui_decimal_text_field("Scale", ui, &mut new_y_scale);
-
-                    if (new_x_scale as f64, new_y_scale as f64) != data.pattern_scale {
-                        let mut new_data = data.clone();
-                        new_data.pattern_scale = (new_x_scale as f64, new_y_scale as f64);
-                        let new_op = ContourOperations::PatternAlongPath { data: new_data };
-
-                        v.begin_layer_modification("PAP dialog modification.");
-                        v.with_active_layer_mut(|layer| layer.outline[contour_idx].operation = Some(new_op.clone()) );
-                        v.end_layer_modification();
-                    }
+            if let Some(ContourOperations::PatternAlongPath { data }) = operation {
+                let old_repeat = repeat_type_to_idx(data.copies.clone());
+                let mut new_repeat = old_repeat;
+
+                let options = [
+                    imgui::im_str!("Single"),
+                    imgui::im_str!("Repeated"),
+                ];
+
+                imgui::ComboBox::new(imgui::im_str!("Mode")).build_simple_string(
+                    ui,
+                    &mut new_repeat,
+                    &options,
+                );
+
+                // we only update the contour and previews when our selection changes
+                if old_repeat != new_repeat {
+                    let mut new_data = data.clone();
+                    new_data.copies = idx_to_repeat_type(new_repeat);
+                    set_pap_contour(v, contour_idx, new_data);
+                }
+
+                let options = [
+                    imgui::im_str!("0"),
+                    imgui::im_str!("1"),
+                    imgui::im_str!("2"),
+                    imgui::im_str!("3"),
+                ];
+
+                let cur_subdivisions = match data.subdivide {
+                    PatternSubdivide::Simple(times) => times,
+                    _ => 0,
+                };
+                let mut new_subdivisions = cur_subdivisions;
+
+                imgui::ComboBox::new(imgui::im_str!("Subdivisions")).build_simple_string(
+                    ui,
+                    &mut new_subdivisions,
+                    &options,
+                );
+
+                if cur_subdivisions != new_subdivisions {
+                    let mut new_data = data.clone();
+                    new_data.subdivide = if new_subdivisions == 0 { PatternSubdivide::Off } else { PatternSubdivide::Simple(new_subdivisions) };
+                    set_pap_contour(v, contour_idx, new_data);
+                }
+
+                let old_flags = [data.center_pattern, data.stretch, data.simplify, data.is_vertical];
+                let mut new_flags = old_flags;
+                ui.checkbox(imgui::im_str!("Center"), &mut new_flags[0]);
+                ui.checkbox(imgui::im_str!("Stretch"), &mut new_flags[1]);
+                ui.checkbox(imgui::im_str!("Simplify"), &mut new_flags[2]);
+                ui.checkbox(imgui::im_str!("Vertical"), &mut new_flags[3]);
+                if old_flags != new_flags {
+                    let mut new_data = data.clone();
+                    new_data.center_pattern = new_flags[0];
+                    new_data.stretch = new_flags[1];
+                    new_data.simplify = new_flags[2];
+                    new_data.is_vertical = new_flags[3];
+                    set_pap_contour(v, contour_idx, new_data);
+                }
+
+                ui.separator();
+
+                if let Some(spacing) = float_field(imgui::im_str!("Spacing"), ui, data.spacing) {
+                    let mut new_data = data.clone();
+                    new_data.spacing = spacing;
+                    set_pap_contour(v, contour_idx, new_data);
+                }
+
+                if let Some(normal_offset) = float_field(imgui::im_str!("Normal offset"), ui, data.normal_offset) {
+                    let mut new_data = data.clone();
+                    new_data.normal_offset = normal_offset;
+                    set_pap_contour(v, contour_idx, new_data);
+                }
+
+                if let Some(tangent_offset) = float_field(imgui::im_str!("Tangent offset"), ui, data.tangent_offset) {
+                    let mut new_data = data.clone();
+                    new_data.tangent_offset = tangent_offset;
+                    set_pap_contour(v, contour_idx, new_data);
+                }
 
+                let mut new_scale = [data.pattern_scale.0 as f32, data.pattern_scale.1 as f32];
+                let entered = ui.input_float2(imgui::im_str!("Scale"), &mut new_scale)
+                    .enter_returns_true(true)
+                    .build();
+                let new_scale = (new_scale[0] as f64, new_scale[1] as f64);
+                if entered && new_scale != data.pattern_scale {
+                    let mut new_data = data.clone();
+                    new_data.pattern_scale = new_scale;
+                    set_pap_contour(v, contour_idx, new_data);
                 }
-                _ => {}
             }
         });
     }
@@ -2,57 +2,156 @@ mod dialog;
 
 use std::rc::Rc;
 
+use MFEKmath::{Evaluate, Piecewise, Vector};
 use glifparser::glif::{ContourOperations, PAPContour, PatternCopies, PatternSubdivide};
+use skulpin::skia_safe::{Paint, PaintStyle};
 
 use crate::{editor::Editor, user_interface::InputPrompt};
 use super::prelude::*;
 
+/// A handle drawn on the selected pattern along path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PAPHandle {
+    /// Where the first copy of the pattern starts; dragging it sets the normal and tangent offsets.
+    Offset,
+    /// Where the second copy starts; dragging it sets the spacing.
+    Spacing,
+}
+
 #[derive(Clone)]
-pub struct PAP {}
+pub struct PAP {
+    drag: Option<PAPHandle>,
+}
 
 impl Tool for PAP {
     fn handle_event(&mut self, v: &mut Editor, i: &mut Interface, event: EditorEvent) {
         match event {
             EditorEvent::MouseEvent { event_type, meta } => {
                 match event_type {
-                    MouseEventType::Moved => { self.mouse_moved(v, meta) }
+                    MouseEventType::Moved => { self.mouse_moved(v, i, meta) }
                     MouseEventType::Pressed => { self.mouse_pressed(v, i, meta) }
-                    //MouseEventType::Released => { self.mouse_released(v, meta) }
+                    MouseEventType::Released => { self.mouse_released(v, meta) }
                     _ => {}
                 }
             }
+            EditorEvent::Draw { skia_canvas } => { self.draw_handles(v, i, skia_canvas) }
             EditorEvent::Ui { ui}=> { self.tool_dialog(v, i, ui) }
             _ => {}
         }
     }
 }
 
+/// The contour at contour_idx and its pattern along path, if it has one.
+fn selected_pap(v: &Editor) -> Option<(usize, PAPContour)> {
+    let contour_idx = v.contour_idx?;
+    v.with_active_layer(|layer| match layer.outline.get(contour_idx).and_then(|c| c.operation.clone()) {
+        Some(ContourOperations::PatternAlongPath { data }) => Some((contour_idx, data)),
+        _ => None,
+    })
+}
+
+/// Where the contour starts, its direction there, and the normal the pattern's y axis is laid
+/// along: the tangent turned a quarter counter-clockwise.
+fn start_frame(v: &Editor, contour_idx: usize) -> Option<(Vector, Vector, Vector)> {
+    v.with_active_layer(|layer| {
+        let contour_pw = Piecewise::from(layer.outline.get(contour_idx)?);
+        let bezier = contour_pw.segs.first()?;
+        let tangent = bezier.tangent_at(0.).normalize();
+        Some((bezier.start_point(), tangent, Vector::from_components(-tangent.y, tangent.x)))
+    })
+}
+
+/// How far one copy of the pattern reaches along the path, before spacing.
+fn pattern_length(data: &PAPContour) -> f64 {
+    let along = data.pattern.iter().flat_map(|c| c.inner.iter()).map(|p| (if data.is_vertical { p.y } else { p.x }) as f64);
+    let (min, max) = along.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), a| (min.min(a), max.max(a)));
+    if min > max { return 0. }
+    (max - min) * if data.is_vertical { data.pattern_scale.1 } else { data.pattern_scale.0 }
+}
+
+/// Where the offset and spacing handles are, measured from the start of the contour as if it ran
+/// straight on.
+fn handle_positions(data: &PAPContour, (start, tangent, normal): (Vector, Vector, Vector)) -> (Vector, Vector) {
+    let offset = start + tangent * data.tangent_offset + normal * data.normal_offset;
+    let spacing = offset + tangent * (pattern_length(data) + data.spacing);
+    (offset, spacing)
+}
+
 impl PAP {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            drag: None,
+        }
+    }
+
+    fn mouse_moved(&mut self, v: &mut Editor, i: &Interface, meta: MouseInfo) {
+        let handle = match self.drag {
+            Some(handle) if i.mouse_info.is_down => handle,
+            _ => return,
+        };
+        let (contour_idx, mut data) = match selected_pap(v) {
+            Some(selected) => selected,
+            None => return,
+        };
+        let frame = match start_frame(v, contour_idx) {
+            Some(frame) => frame,
+            None => return,
+        };
+        let (start, tangent, normal) = frame;
+        let mouse = Vector::from_components(calc_x(meta.position.0) as f64, calc_y(meta.position.1) as f64);
+
+        match handle {
+            PAPHandle::Offset => {
+                data.normal_offset = (mouse - start).dot(normal);
+                // Ctrl keeps the pattern where it is along the path.
+                if !meta.modifiers.ctrl {
+                    data.tangent_offset = (mouse - start).dot(tangent);
+                }
+            }
+            PAPHandle::Spacing => {
+                let (offset, _) = handle_positions(&data, frame);
+                let length = pattern_length(&data);
+                // Copies must move forwards, or the path would never be filled.
+                data.spacing = ((mouse - offset).dot(tangent) - length).max(1. - length);
+            }
+        }
+
+        if !v.is_modifying() { v.begin_layer_modification("Move PAP handle.") }
+        v.with_active_layer_mut(|layer| {
+            layer.outline[contour_idx].operation = Some(ContourOperations::PatternAlongPath { data: data.clone() });
+        });
     }
 
-    fn mouse_moved(&mut self, _v: &mut Editor, _meta: MouseInfo) {
+    fn mouse_released(&mut self, v: &mut Editor, _meta: MouseInfo) {
+        if self.drag.take().is_some() {
+            v.end_layer_modification();
+        }
     }
 
+    // Clicking a handle of the selected pattern starts dragging it. Clicking a contour picks it for
+    // the dialog, first asking for a pattern if it has no operation yet.
     fn mouse_pressed(&mut self, v: &mut Editor, i: &mut Interface, meta: MouseInfo) {
+        if let Some(handle) = self.clicked_handle(v, i, meta) {
+            self.drag = Some(handle);
+            return;
+        }
+
         if let Some((ci, pi, _wh)) = clicked_point_or_handle(v, i, meta.raw_position, None) {
+            v.contour_idx = Some(ci);
+            v.point_idx = Some(pi);
+
             let layer_op = v.with_active_layer(|layer| layer.outline[ci].operation.clone() );
-            if let Some(_op) = layer_op {
-                
-            }
-            else {
+            if layer_op.is_none() {
                 i.push_prompt(InputPrompt::Layer{
                     label: "Select a pattern.".to_string(),
-                    func: Rc::new(move |editor, source_layer| {
-                        editor.contour_idx = Some(ci);
-                        editor.point_idx = Some(pi);
+                    func: Rc::new(move |editor, source_idx| {
+                        let pattern = editor.with_glyph(|glif| glif.layers[source_idx].outline.clone());
 
                         editor.begin_layer_modification("Added PAP contour.");
                         editor.with_active_layer_mut(|layer| {
                             layer.outline[ci].operation = Some(ContourOperations::PatternAlongPath {
                                 data: PAPContour {
-                                    pattern: source_layer.outline.clone(),
+                                    pattern: pattern.clone(),
                                     copies: PatternCopies::Repeated,
                                     subdivide: PatternSubdivide::Off,
                                     is_vertical: false,
@@ -70,6 +169,66 @@ impl PAP {
                     }),
                 });
             }
-        }   
+        }
+    }
+
+    fn clicked_handle(&self, v: &Editor, i: &Interface, meta: MouseInfo) -> Option<PAPHandle> {
+        let (contour_idx, data) = selected_pap(v)?;
+        let (offset, spacing) = handle_positions(&data, start_frame(v, contour_idx)?);
+
+        let size = ((POINT_RADIUS * 2.) + (POINT_STROKE_THICKNESS * 2.)) * (1. / i.viewport.factor);
+        let mouse = SkPoint::new(meta.position.0 as f32, meta.position.1 as f32);
+        let hit = |pos: Vector| {
+            let corner = SkPoint::new(calc_x(pos.x as f32) - (size / 2.), calc_y(pos.y as f32) - (size / 2.));
+            SkRect::from_point_and_size(corner, (size, size)).contains(mouse)
+        };
+
+        if matches!(data.copies, PatternCopies::Repeated) && hit(spacing) {
+            Some(PAPHandle::Spacing)
+        } else if hit(offset) {
+            Some(PAPHandle::Offset)
+        } else {
+            None
+        }
+    }
+
+    // A bar from the start of the selected contour to where its pattern starts, and on to where the
+    // next copy starts, with a handle at each end.
+    fn draw_handles(&self, v: &Editor, i: &Interface, canvas: &mut Canvas) {
+        let (contour_idx, data) = match selected_pap(v) {
+            Some(selected) => selected,
+            None => return,
+        };
+        let frame = match start_frame(v, contour_idx) {
+            Some(frame) => frame,
+            None => return,
+        };
+        let (offset, spacing) = handle_positions(&data, frame);
+        let repeated = matches!(data.copies, PatternCopies::Repeated);
+        let factor = i.viewport.factor;
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(RIB_STROKE);
+        paint.set_stroke_width(HANDLEBAR_THICKNESS * (1. / factor));
+        paint.set_style(PaintStyle::Stroke);
+
+        let mut path = SkPath::new();
+        path.move_to((calc_x(frame.0.x as f32), calc_y(frame.0.y as f32)));
+        path.line_to((calc_x(offset.x as f32), calc_y(offset.y as f32)));
+        if repeated {
+            path.line_to((calc_x(spacing.x as f32), calc_y(spacing.y as f32)));
+        }
+        canvas.draw_path(&path, &paint);
+
+        let mut handle_paint = Paint::default();
+        handle_paint.set_anti_alias(true);
+        handle_paint.set_color(HANDLE_FILL);
+        handle_paint.set_style(PaintStyle::Fill);
+        let radius = POINT_RADIUS * (1. / factor);
+        canvas.draw_circle((calc_x(offset.x as f32), calc_y(offset.y as f32)), radius, &handle_paint);
+        if repeated {
+            canvas.draw_circle((calc_x(spacing.x as f32), calc_y(spacing.y as f32)), radius, &handle_paint);
+        }
     }
 }
@@ -1,4 +1,6 @@
-use crate::user_interface::Interface;
+use std::rc::Rc;
+
+use crate::user_interface::{InputPrompt, Interface};
 use glifparser::glif::{CapType, ContourOperations, JoinType};
 use super::VWS;
 use super::super::prelude::*;
@@ -120,7 +122,66 @@ impl VWS {
         }
     }
 
-    pub fn build_vws_settings_window(&self, v: &mut Editor, i: &Interface, ui: &mut imgui::Ui) {
+    // Copying the stroke to paste on other contours, and the library of saved profiles.
+    fn build_and_check_vws_profile(&mut self, v: &mut Editor, i: &mut Interface, ui: &imgui::Ui) {
+        ui.separator();
+        ui.text(imgui::im_str!("Profile"));
+
+        if ui.button(imgui::im_str!("Copy##profile"), [0., 0.]) {
+            v.copy_vws_profile();
+        }
+        if v.vws_profile.is_some() {
+            ui.same_line(0.);
+            if ui.button(imgui::im_str!("Paste##profile"), [0., 0.]) {
+                v.paste_vws_profile();
+            }
+            ui.same_line(0.);
+            if ui.button(imgui::im_str!("Save##profile"), [0., 0.]) {
+                i.push_prompt(InputPrompt::Text {
+                    label: "Profile name:".to_string(),
+                    default: String::new(),
+                    func: Rc::new(|editor, name| {
+                        if name.is_empty() { return }
+                        if let Err(e) = editor.save_vws_profile(&name) {
+                            log::error!("{}", e);
+                        }
+                    }),
+                });
+            }
+        }
+
+        let names = match v.vws_profile_names() {
+            Ok(names) => names,
+            Err(e) => {
+                ui.text_wrapped(&imgui::im_str!("{}", e));
+                return;
+            }
+        };
+        if names.is_empty() { return }
+        self.library_idx = self.library_idx.min(names.len() - 1);
+
+        let options: Vec<imgui::ImString> = names.iter().map(|n| imgui::ImString::new(n.as_str())).collect();
+        let options: Vec<&imgui::ImStr> = options.iter().map(|n| n.as_ref()).collect();
+        imgui::ComboBox::new(imgui::im_str!("Library")).build_simple_string(
+            ui,
+            &mut self.library_idx,
+            &options,
+        );
+
+        if ui.button(imgui::im_str!("Load##library"), [0., 0.]) {
+            if let Err(e) = v.load_vws_profile(&names[self.library_idx]) {
+                i.report_error(e);
+            }
+        }
+        ui.same_line(0.);
+        if ui.button(imgui::im_str!("Delete##library"), [0., 0.]) {
+            if let Err(e) = v.delete_vws_profile(&names[self.library_idx]) {
+                i.report_error(e);
+            }
+        }
+    }
+
+    pub fn build_vws_settings_window(&mut self, v: &mut Editor, i: &mut Interface, ui: &mut imgui::Ui) {
         let (tx, ty, tw, th) = i.get_tools_dialog_rect();
 
         // if we don't have a contour selected we don't draw this
@@ -148,6 +209,7 @@ impl VWS {
             .build(ui, || {
                 self.build_and_check_vws_cap_combo(v, ui);
                 self.build_and_check_vws_join_combo(v, ui);
+                self.build_and_check_vws_profile(v, i, ui);
             });
     }
 }
@@ -14,6 +14,7 @@ pub struct VWS {
     constrain: bool,
     all: bool,
     handle: Option<WhichHandle>,
+    library_idx: usize, // the profile picked in the dialog's library list
 }
 
 impl Tool for VWS {
@@ -43,6 +44,7 @@ impl VWS {
             constrain: false,
             all: false,
             handle: None,
+            library_idx: 0,
         }
     }
 
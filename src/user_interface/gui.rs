@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{editor::Editor, tools::{EditorEvent, ToolEnum}, user_interface::InputPrompt};
+use crate::{editor::{Editor, groups, history::DiffSide}, tools::{EditorEvent, ToolEnum}, user_interface::InputPrompt};
 
 use super::{Interface, icons};
 
@@ -13,11 +13,15 @@ pub const TOOLBOX_HEIGHT: f32 = 300.;
 pub const LAYERBOX_WIDTH: f32 = 250.;
 pub const LAYERBOX_HEIGHT: f32 = 250.;
 
+pub const HISTORYBOX_WIDTH: f32 = 250.;
+pub const HISTORYBOX_HEIGHT: f32 = 350.;
+
 use glifparser::glif::LayerOperation;
 use imgui::{self, ColorStackToken, Context, DrawData, FontId, Key, StyleColor, StyleVar};
 use imgui_sdl2::ImguiSdl2;
 use imgui_skia_renderer::Renderer;
 use sdl2::{event::Event, mouse::MouseState, video::Window};
+use skulpin::skia_safe::PathOp;
 
 pub struct ImguiManager {
     pub imgui_context: Context,
@@ -166,31 +170,90 @@ impl ImguiManager {
         ui.same_line(0.);
         ui.button(unsafe { imgui::ImStr::from_utf8_with_nul_unchecked(icons::ARROWUP) }, [0., 0.]);
         if ui.is_item_clicked(imgui::MouseButton::Left) {
-            if active_layer != 0 {
-                let _start_layer_type = v.with_glyph(|glif| glif.layers[active_layer].operation.clone());
-                let _target_layer_type = v.with_glyph(|glif| glif.layers[active_layer-1].operation.clone());
-    
-                v.swap_layers(active_layer, active_layer-1, true);
-            }
+            v.move_layer(active_layer, true);
         }
     
         let layer_count = v.get_layer_count();
         ui.same_line(0.);
         ui.button(unsafe { imgui::ImStr::from_utf8_with_nul_unchecked(icons::ARROWDOWN) }, [0., 0.]);
         if ui.is_item_clicked(imgui::MouseButton::Left) {
-            if active_layer != layer_count-1 {
-                v.swap_layers(active_layer, active_layer+1, true);
+            v.move_layer(active_layer, false);
+        }
+    
+        ui.button(imgui::im_str!("Duplicate"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            v.duplicate_layer(active_layer);
+        }
+
+        ui.same_line(0.);
+        if v.can_merge_layer_down(active_layer) {
+            ui.button(imgui::im_str!("Merge"), [0., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                v.merge_layer_down(active_layer);
+            }
+        } else {
+            let disabled = ui.push_style_var(StyleVar::Alpha(0.5));
+            ui.button(imgui::im_str!("Merge"), [0., 0.]);
+            disabled.pop(ui);
+        }
+
+        for (label, remove) in &[(imgui::im_str!("Move to"), true), (imgui::im_str!("Copy to"), false)] {
+            let remove = *remove;
+            ui.same_line(0.);
+            ui.button(label, [0., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                i.push_prompt(InputPrompt::Layer {
+                    label: format!("{} layer:", if remove { "Move selection to" } else { "Copy selection to" }),
+                    func: Rc::new(move |editor, dest| editor.transfer_selection(dest, remove)),
+                });
             }
         }
+
+        ui.button(imgui::im_str!("Group"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            v.group_layer(active_layer);
+        }
+        ui.same_line(0.);
+        ui.button(imgui::im_str!("Ungroup"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            v.ungroup_layer(active_layer);
+        }
+        ui.same_line(0.);
+        ui.button(imgui::im_str!("Into group"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            v.indent_layer(active_layer);
+        }
+        ui.same_line(0.);
+        ui.button(imgui::im_str!("Out of group"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            v.outdent_layer(active_layer);
+        }
     
         pop_me.pop(ui);
         
         ui.separator();
     
         for layer in 0 .. layer_count {
+            if !v.with_glyph(|glif| groups::is_listed(&glif.layers, &i.collapsed_groups, layer)) { continue }
+
             let layer_op = v.with_glyph(|glif| glif.layers[layer].operation.clone());
-            let layer_temp_name = imgui::im_str!("{0}", v.with_glyph(|glif| { glif.layers[layer].name.clone() }));
+            let layer_temp_name = imgui::im_str!("{0}", v.with_glyph(|glif| { groups::display_name(&glif.layers[layer]).to_string() }));
             let im_str = imgui::ImString::from(layer_temp_name);
+            let (depth, is_group, key) = v.with_glyph(|glif| (groups::depth(&glif.layers[layer]), groups::is_group(&glif.layers, layer), groups::group_key(&glif.layers, layer)));
+    
+            // Layers in groups are indented under them, and groups get a button to collapse them.
+            if depth > 0 {
+                ui.dummy([14. * depth as f32, 0.]);
+                ui.same_line(0.);
+            }
+            if is_group {
+                let collapsed = i.collapsed_groups.contains(&key);
+                ui.button(if collapsed { imgui::im_str!("+") } else { imgui::im_str!("-") }, [0., 0.]);
+                if ui.is_item_clicked(imgui::MouseButton::Left) {
+                    if collapsed { i.collapsed_groups.remove(&key); } else { i.collapsed_groups.insert(key); }
+                }
+                ui.same_line(0.);
+            }
     
             let font_token = ui.push_font(FONT_IDS.with(|ids| { ids.borrow()[1] }));
             let no_padding = ui.push_style_var(StyleVar::ItemSpacing([0., 0.]));
@@ -219,17 +282,8 @@ impl ImguiManager {
             if ui.is_item_clicked(imgui::MouseButton::Left) {
                 i.push_prompt(InputPrompt::Text {
                     label: "Layer name:".to_string(),
-                    default: v.with_glyph(|glif| glif.layers[layer].name.clone()),
-                    func: Rc::new(move |editor, string| {
-                        let active_layer = editor.get_active_layer();
-                        editor.set_active_layer(layer);
-        
-                        editor.begin_layer_modification("Renamed layer.");
-                        editor.with_active_layer_mut(|layer| layer.name = string.clone());
-                        editor.end_layer_modification();
-        
-                        editor.set_active_layer(active_layer);
-                    }),
+                    default: v.with_glyph(|glif| groups::display_name(&glif.layers[layer]).to_string()),
+                    func: Rc::new(move |editor, string| editor.rename_layer(layer, &string)),
                 });
             }
             ui.same_line(0.);
@@ -329,10 +383,138 @@ impl ImguiManager {
         }
     }
     
+    /// The A and B buttons that put `side` on either end of the history diff.
+    fn build_and_check_diff_buttons(v: &mut Editor, ui: &imgui::Ui, side: DiffSide) {
+        let (before, after) = v.history().diff;
+        for &(is_before, chosen) in &[(true, before == Some(side)), (false, after == Some(side))] {
+            let pop_me = if chosen { None } else { Some(ui.push_style_color(imgui::StyleColor::Button, [0., 0., 0., 0.2])) };
+            ui.button(if is_before { imgui::im_str!("A") } else { imgui::im_str!("B") }, [0., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                let toggled = if chosen { None } else { Some(side) };
+                if is_before {
+                    v.set_history_diff(toggled, after);
+                } else {
+                    v.set_history_diff(before, toggled);
+                }
+            }
+            if let Some(p) = pop_me {
+                p.pop(ui);
+            }
+            ui.same_line(0.);
+        }
+    }
+
+    pub fn build_and_check_history(v: &mut Editor, i: &mut Interface, ui: &imgui::Ui) {
+        ui.button(imgui::im_str!("Pin snapshot"), [0., 0.]);
+        if ui.is_item_clicked(imgui::MouseButton::Left) {
+            i.push_prompt(InputPrompt::Text {
+                label: "Snapshot name:".to_string(),
+                default: String::new(),
+                func: Rc::new(move |editor, name| editor.pin_snapshot(name)),
+            });
+        }
+        if v.history().diff != (None, None) {
+            ui.same_line(0.);
+            ui.button(imgui::im_str!("Clear diff"), [0., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                v.set_history_diff(None, None);
+            }
+        }
+
+        ui.separator();
+
+        // Snapshots, plus the glyph as it is now so it can be diffed against one.
+        let snapshots: Vec<(String, bool)> = v.history().snapshots.iter().map(|s| (s.name.clone(), s.reachable)).collect();
+        let mut delete = None;
+        for (idx, (name, reachable)) in snapshots.iter().enumerate() {
+            let id = ui.push_id(idx as i32);
+            Self::build_and_check_diff_buttons(v, ui, DiffSide::Snapshot(idx));
+            ui.button(imgui::im_str!("x"), [0., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                delete = Some(idx);
+            }
+            ui.same_line(0.);
+            // Snapshots that history no longer leads to can only be diffed.
+            let dimmed = if *reachable { None } else { Some(ui.push_style_color(StyleColor::Text, [0.5, 0.5, 0.5, 1.])) };
+            ui.button(&imgui::im_str!("{}", name), [-1., 0.]);
+            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                v.restore_snapshot(idx);
+            }
+            if let Some(p) = dimmed {
+                p.pop(ui);
+            }
+            id.pop(ui);
+        }
+        if let Some(idx) = delete {
+            v.delete_snapshot(idx);
+        }
+        Self::build_and_check_diff_buttons(v, ui, DiffSide::Current);
+        ui.text(imgui::im_str!("Current glyph"));
+
+        ui.separator();
+
+        // Every point in history, oldest first. A row is the glyph after its edit was made; redoable
+        // edits are dimmed.
+        let depth = v.history().depth();
+        let (undo, redo) = v.history().stacks();
+        let rows: Vec<String> = std::iter::once("Opened".to_string())
+            .chain(undo.iter().map(|e| e.description().to_string()))
+            .chain(redo.iter().rev().map(|e| e.description().to_string()))
+            .collect();
+
+        let mut jump = None;
+        imgui::ChildWindow::new(imgui::im_str!("##history_entries")).build(ui, || {
+            for (row_depth, description) in rows.iter().enumerate() {
+                let id = ui.push_id(row_depth as i32);
+                let dimmed = if row_depth > depth { Some(ui.push_style_color(StyleColor::Text, [0.5, 0.5, 0.5, 1.])) } else { None };
+                if imgui::Selectable::new(&imgui::im_str!("{}", description)).selected(row_depth == depth).build(ui) {
+                    jump = Some(row_depth);
+                }
+                if let Some(p) = dimmed {
+                    p.pop(ui);
+                }
+                id.pop(ui);
+            }
+        });
+        if let Some(row_depth) = jump {
+            v.jump_to_history(row_depth);
+        }
+    }
+
+    /// Boolean operations between the selected contours, as in the console's `union`, `subtract`,
+    /// `intersect` and `xor`, then `apply` and `removeoverlap`.
+    fn build_and_check_contours_menu(v: &mut Editor, ui: &imgui::Ui) {
+        let enabled = v.can_combine_contours();
+        for &(label, op) in &[
+            (imgui::im_str!("Union"), PathOp::Union),
+            (imgui::im_str!("Subtract"), PathOp::Difference),
+            (imgui::im_str!("Intersect"), PathOp::Intersect),
+            (imgui::im_str!("Exclude (XOR)"), PathOp::XOR),
+        ] {
+            if imgui::MenuItem::new(label).enabled(enabled).build(ui) {
+                v.contour_boolean(op);
+            }
+        }
+        ui.separator();
+        if imgui::MenuItem::new(imgui::im_str!("Apply operation")).enabled(v.can_apply_contour_operations()).build(ui) {
+            v.apply_contour_operations();
+        }
+        if imgui::MenuItem::new(imgui::im_str!("Remove overlap")).build(ui) {
+            v.remove_overlap();
+        }
+    }
+
     pub fn build_imgui_ui<'ui>(context: &'ui mut Context, imsdl2: &mut ImguiSdl2, v: &mut Editor, i: &mut Interface, mouse_state: &MouseState) -> &'ui DrawData {
         imsdl2.prepare_frame(context.io_mut(), &i.sdl_window, mouse_state);
         let mut ui = context.frame();
 
+        ui.main_menu_bar(|| {
+            ui.menu(imgui::im_str!("Contours"), true, || {
+                Self::build_and_check_contours_menu(v, &ui);
+            });
+        });
+        let menu_bar_height = ui.frame_height();
+
         imgui::Window::new(imgui::im_str!("Tools"))
             .bg_alpha(1.) // See comment on fn redraw_skia
             .flags(
@@ -341,7 +523,7 @@ impl ImguiManager {
                     | imgui::WindowFlags::NO_COLLAPSE,
             )
             .position(
-                [TOOLBOX_OFFSET_X, TOOLBOX_OFFSET_Y],
+                [TOOLBOX_OFFSET_X, TOOLBOX_OFFSET_Y + menu_bar_height],
                 imgui::Condition::Always,
             )
             .size([TOOLBOX_WIDTH, TOOLBOX_HEIGHT+60.], imgui::Condition::Always)
@@ -372,6 +554,21 @@ impl ImguiManager {
             .build(&ui, || {
                 Self::build_and_check_layer_list(v, i, &ui)
             });
+
+        if i.history_panel {
+            imgui::Window::new(imgui::im_str!("History"))
+                .bg_alpha(1.)
+                .flags(
+                            imgui::WindowFlags::NO_RESIZE
+                        | imgui::WindowFlags::NO_MOVE
+                        | imgui::WindowFlags::NO_COLLAPSE
+                )
+                .position([TOOLBOX_OFFSET_X, i.viewport.winsize.1 as f32 - TOOLBOX_OFFSET_Y - HISTORYBOX_HEIGHT], imgui::Condition::Always)
+                .size([HISTORYBOX_WIDTH, HISTORYBOX_HEIGHT], imgui::Condition::Always)
+                .build(&ui, || {
+                    Self::build_and_check_history(v, i, &ui)
+                });
+        }
     
             Self::build_and_check_prompts(v, i, &mut ui);
     
@@ -510,7 +707,7 @@ impl ImguiManager {
                         }
                         ui.button(&im_str, [-1., 0.]);
                         if ui.is_item_clicked(imgui::MouseButton::Left) {
-                            func(v, v.with_glyph(|glif| glif.layers[layer].clone() ));
+                            func(v, layer);
                             i.pop_prompt();
                         }
                         if let Some(p) = pop_me {
@@ -520,6 +717,77 @@ impl ImguiManager {
                     }
                 });
             }
+
+            InputPrompt::Glyph => {
+                imgui::Window::new(&imgui::im_str!("Go to glyph"))
+                .bg_alpha(1.) // See comment on fn redraw_skia
+                .flags(
+                        imgui::WindowFlags::NO_RESIZE
+                        | imgui::WindowFlags::NO_COLLAPSE,
+                )
+                .position_pivot([0.5, 0.5])
+                .position(
+                    [(i.viewport.winsize.0/2) as f32, (i.viewport.winsize.1/2) as f32],
+                    imgui::Condition::Always,
+                )
+                .size([LAYERBOX_WIDTH, TOOLBOX_HEIGHT * 1.5], imgui::Condition::Always)
+                .focused(true)
+                .build(ui, || {
+                    let current_saved = v.history().is_saved();
+                    let glyphs: Vec<(usize, String, bool)> = match v.font.as_ref() {
+                        Some(font) => font.glyphs.iter().enumerate()
+                            .map(|(idx, (name, _))| (idx, name.clone(), if idx == font.current { !current_saved } else { font.is_edited(idx) }))
+                            .collect(),
+                        None => vec![],
+                    };
+
+                    GLYPH_FILTER.with(|filter| {
+                        ui.push_item_width(-1.);
+                        ui.input_text(imgui::im_str!("##glyph_filter"), &mut filter.borrow_mut()).build();
+                        let filter = filter.borrow().to_str().to_owned();
+
+                        let mut chosen = None;
+                        for (idx, name, edited) in glyphs.iter().filter(|(_, name, _)| name.contains(&filter)) {
+                            // Glyphs with unsaved changes are marked.
+                            let label = if *edited { imgui::im_str!("{} *", name) } else { imgui::im_str!("{}", name) };
+                            ui.button(&label, [-1., 0.]);
+                            if ui.is_item_clicked(imgui::MouseButton::Left) {
+                                chosen = Some(*idx);
+                            }
+                        }
+
+                        if let Some(idx) = chosen {
+                            i.pop_prompt();
+                            if let Err(e) = crate::io::switch_glyph(v, i, idx) {
+                                i.report_error(e);
+                            }
+                        }
+                    });
+                });
+            }
+
+            InputPrompt::Message { title, message } => {
+                imgui::Window::new(&imgui::im_str!("{}", title))
+                .bg_alpha(1.) // See comment on fn redraw_skia
+                .flags(
+                        imgui::WindowFlags::NO_RESIZE
+                        | imgui::WindowFlags::NO_COLLAPSE,
+                )
+                .position_pivot([0.5, 0.5])
+                .position(
+                    [(i.viewport.winsize.0/2) as f32, (i.viewport.winsize.1/2) as f32],
+                    imgui::Condition::Always,
+                )
+                .size([TOOLBOX_HEIGHT * 2., TOOLBOX_WIDTH+10.], imgui::Condition::Always)
+                .focused(true)
+                .build(ui, || {
+                    ui.text_wrapped(&imgui::im_str!("{}", message));
+                    ui.button(imgui::im_str!("OK"), [-1., 0.]);
+                    if ui.is_item_clicked(imgui::MouseButton::Left) || ui.is_key_down(Key::Enter) {
+                        i.pop_prompt();
+                    }
+                });
+            }
         }
     }
 
@@ -534,5 +802,6 @@ impl ImguiManager {
 }
 
 thread_local! { pub static PROMPT_STR: RefCell<imgui::ImString> = RefCell::new(imgui::ImString::new("")); }
+thread_local! { pub static GLYPH_FILTER: RefCell<imgui::ImString> = RefCell::new(imgui::ImString::with_capacity(64)); }
 thread_local! { pub static PROMPT_CLR: RefCell<[f32; 4]> = RefCell::new([0., 0., 0., 1.]); }
 thread_local! { pub static FONT_IDS: RefCell<Vec<FontId>> = RefCell::new(vec!()); }
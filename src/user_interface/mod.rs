@@ -1,4 +1,4 @@
-use std::{rc::Rc};
+use std::{collections::HashSet, rc::Rc};
 
 use imgui::{self, Context};
 use imgui_sdl2::ImguiSdl2;
@@ -9,10 +9,10 @@ use ::skulpin::rafx::api::RafxExtents2D;
 
 use crate::renderer;
 use crate::editor::Editor;
+use crate::util::error::MFEKglifError;
 use crate::renderer::constants::HEIGHT;
 use crate::renderer::constants::WIDTH;
 pub use crate::user_interface::mouse_input::MouseInfo;
-use glifparser::glif::{Layer, MFEKPointData};
 use sdl2::{Sdl, video::Window};
 
 use self::grid::Grid;
@@ -39,6 +39,10 @@ pub struct Interface {
     pub grid: Option<Grid>,
     pub mouse_info: MouseInfo,
     pub viewport: Viewport,
+    pub history_panel: bool,
+    /// The layer groups collapsed in the layer list, by `groups::group_key`. This is only how the
+    /// list looks, so it isn't kept in the glyph or its history.
+    pub collapsed_groups: HashSet<(String, usize)>,
 }
 
 impl Interface {
@@ -53,6 +57,8 @@ impl Interface {
             grid: None,
             mouse_info: MouseInfo::default(),
             viewport: Viewport::default(),
+            history_panel: false,
+            collapsed_groups: HashSet::new(),
         };
 
         ret.viewport.winsize = (WIDTH as u32, HEIGHT as u32);
@@ -105,6 +111,14 @@ impl Interface {
     pub fn push_prompt(&mut self, prompt: InputPrompt) {
         self.prompts.push(prompt);
     }
+
+    /// Logs a failed load/save/export and tells the user about it. Cancelling a file dialog is not
+    /// worth a prompt.
+    pub fn report_error(&mut self, error: MFEKglifError) {
+        if let MFEKglifError::NoFileChosen = error { return }
+        log::error!("{}", error);
+        self.push_prompt(InputPrompt::Message { title: "Error".to_string(), message: error.to_string() });
+    }
 }
 
 #[derive(Clone)]
@@ -119,8 +133,15 @@ pub enum InputPrompt {
         default: [f32; 4],
         func: Rc<dyn Fn(&mut Editor, Option<[f32; 4]>)>
     },
+    /// Picks a layer of the glyph, by index.
     Layer {
         label: String,
-        func: Rc<dyn Fn(&mut Editor, Layer<MFEKPointData>)>
-    }
+        func: Rc<dyn Fn(&mut Editor, usize)>
+    },
+    Message {
+        title: String,
+        message: String,
+    },
+    /// Picks a glyph of the open UFO to switch to.
+    Glyph,
 }
\ No newline at end of file
@@ -3,17 +3,64 @@ use git_version::git_version;
 
 use clap; //argparse lib
 
+use crate::editor::batch;
+use crate::editor::PreviewMode;
+use crate::renderer::offscreen::RenderFormat;
+use crate::util::messages::MessageFormat;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum HeadlessMode {
     None,
     Export,
-    RunScript, // unused until scripting support added
+    ExportBatch,
+    RunScript,
+    Render,
+    Check,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderArgs {
+    pub output: String,
+    pub format: RenderFormat,
+    pub size: (u32, u32),
+    pub padding: f32,
+    pub preview_mode: PreviewMode,
+    /// Layers to draw, by index. All visible layers if None.
+    pub layers: Option<Vec<usize>>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Args {
     pub filename: Option<String>,
     pub headless_mode: HeadlessMode,
+    pub script: Option<String>,
+    pub render: Option<RenderArgs>,
+    pub watch: bool,
+    pub message_format: MessageFormat,
+    pub history_journal: bool,
+    /// In bytes.
+    pub history_limit: usize,
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let mut parts = s.splitn(2, 'x');
+    let w = parts.next().unwrap().parse::<u32>().map_err(|e| e.to_string())?;
+    let h = match parts.next() {
+        Some(h) => h.parse::<u32>().map_err(|e| e.to_string())?,
+        None => w,
+    };
+    if w == 0 || h == 0 { return Err("size must not be zero".to_string()) }
+    Ok((w, h))
+}
+
+// MiB to bytes.
+fn parse_history_limit(s: &str) -> Result<usize, String> {
+    let mib = s.parse::<usize>().map_err(|e| e.to_string())?;
+    mib.checked_mul(1 << 20).ok_or_else(|| "history limit too large".to_string())
+}
+
+fn parse_layers(s: &str) -> Result<Vec<usize>, String> {
+    s.split(',').map(|l| l.trim().parse::<usize>().map_err(|e| e.to_string())).collect()
 }
 
 pub fn parse_args() -> Args {
@@ -22,7 +69,7 @@ pub fn parse_args() -> Args {
         .about("Glyph editor, Modular Font Editor K Project")
         .arg(
             clap::Arg::with_name("GLIF")
-                .help("Input UFO format .glif file")
+                .help("Input UFO format .glif file, or a .ufo directory to edit its glyphs one after another. With --export, may also be a glob of .glif files")
                 .index(1),
         )
         .arg(
@@ -32,9 +79,142 @@ pub fn parse_args() -> Args {
                 .takes_value(false)
                 .help(r#"Export glyph to current name (you should make a copy so as not to lose MFEK data)"#)
         )
+        .arg(
+            clap::Arg::with_name("watch")
+                .long("watch")
+                .short("w")
+                .takes_value(false)
+                .requires("export")
+                .help(r#"With --export, keep running and export again whenever the .glif or its images change"#)
+        )
+        .arg(
+            clap::Arg::with_name("check")
+                .long("check")
+                .short("c")
+                .takes_value(false)
+                .conflicts_with_all(&["export", "script", "render"])
+                .help(r#"Check the glyph for problems such as open contours in boolean layers or mismatched VWS handles, print them, and exit non-zero if any is an error"#)
+        )
+        .arg(
+            clap::Arg::with_name("message-format")
+                .long("message-format")
+                .takes_value(true)
+                .possible_values(&["human", "json"])
+                .default_value("human")
+                .help(r#"How headless modes report progress. `json` prints one JSON object per event to stdout"#)
+        )
+        .arg(
+            clap::Arg::with_name("history-journal")
+                .long("history-journal")
+                .takes_value(false)
+                .help(r#"Keep the undo history of each glyph on disk when saving, and restore it when the glyph is opened again"#)
+        )
+        .arg(
+            clap::Arg::with_name("history-limit")
+                .long("history-limit")
+                .takes_value(true)
+                .value_name("MIB")
+                .default_value("256") // editor::history::DEFAULT_MEMORY_LIMIT_MB
+                .validator(|s| parse_history_limit(&s).map(|_| ()))
+                .help(r#"About how much memory, in MiB, each glyph's undo history may use before its oldest entries are forgotten"#)
+        )
+        .arg(
+            clap::Arg::with_name("script")
+                .long("script")
+                .short("s")
+                .takes_value(true)
+                .value_name("SCRIPT")
+                .conflicts_with_all(&["export", "render"])
+                .help(r#"Run a Rhai script against the glyph without opening a window. The script gets the glyph as `glyph`; call glyph.save() or glyph.export() to write it"#)
+        )
+        .arg(
+            clap::Arg::with_name("render")
+                .long("render")
+                .short("r")
+                .takes_value(true)
+                .value_name("OUT")
+                .conflicts_with("export")
+                .validator(|s| RenderFormat::from_filename(&s).map(|_| ()).ok_or_else(|| "output must end in .png, .svg or .pdf".to_string()))
+                .help(r#"Render the glyph to a .png, .svg or .pdf file without opening a window"#)
+        )
+        .arg(
+            clap::Arg::with_name("render-size")
+                .long("render-size")
+                .takes_value(true)
+                .value_name("WxH")
+                .default_value("512")
+                .validator(|s| parse_size(&s).map(|_| ()))
+                .help(r#"Size of the rendering in pixels (or points, for PDF), as WIDTHxHEIGHT or one number for a square"#)
+        )
+        .arg(
+            clap::Arg::with_name("render-padding")
+                .long("render-padding")
+                .takes_value(true)
+                .value_name("PX")
+                .default_value("16")
+                .validator(|s| s.parse::<f32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help(r#"Empty space around the glyph, in the same units as --render-size"#)
+        )
+        .arg(
+            clap::Arg::with_name("render-mode")
+                .long("render-mode")
+                .takes_value(true)
+                .possible_values(&["paper", "editor"])
+                .default_value("paper")
+                .help(r#"`paper` draws the filled glyph as a proof; `editor` draws it as the editor does, with points and handles"#)
+        )
+        .arg(
+            clap::Arg::with_name("render-layers")
+                .long("render-layers")
+                .takes_value(true)
+                .value_name("LAYERS")
+                .requires("render")
+                .validator(|s| parse_layers(&s).map(|_| ()))
+                .help(r#"Comma-separated indices of the layers to draw, e.g. 0,2. Defaults to all visible layers"#)
+        )
         .get_matches();
+    let filename = matches.value_of("GLIF").map(|s| s.to_string());
+    let headless_mode = if matches.is_present("export") {
+        if filename.as_ref().map(|f| batch::is_batch_input(f)).unwrap_or(false) {
+            if matches.is_present("watch") {
+                clap::Error::with_description("--watch only works on a single .glif", clap::ErrorKind::ArgumentConflict).exit()
+            }
+            HeadlessMode::ExportBatch
+        } else {
+            HeadlessMode::Export
+        }
+    } else if matches.is_present("script") {
+        HeadlessMode::RunScript
+    } else if matches.is_present("render") {
+        HeadlessMode::Render
+    } else if matches.is_present("check") {
+        HeadlessMode::Check
+    } else {
+        HeadlessMode::None
+    };
+    // Values were checked by the validators above, so the unwraps can't fail.
+    let render = matches.value_of("render").map(|output| RenderArgs {
+        output: output.to_string(),
+        format: RenderFormat::from_filename(output).unwrap(),
+        size: parse_size(matches.value_of("render-size").unwrap()).unwrap(),
+        padding: matches.value_of("render-padding").unwrap().parse().unwrap(),
+        preview_mode: match matches.value_of("render-mode").unwrap() {
+            "editor" => PreviewMode::None,
+            _ => PreviewMode::Paper,
+        },
+        layers: matches.value_of("render-layers").map(|l| parse_layers(l).unwrap()),
+    });
     Args {
-        filename: matches.value_of("GLIF").map(|s| s.to_string()),
-        headless_mode: matches.is_present("export").then(||HeadlessMode::Export).unwrap_or_else(||HeadlessMode::None),
+        filename,
+        headless_mode,
+        script: matches.value_of("script").map(|s| s.to_string()),
+        render,
+        watch: matches.is_present("watch"),
+        message_format: match matches.value_of("message-format").unwrap() {
+            "json" => MessageFormat::Json,
+            _ => MessageFormat::Human,
+        },
+        history_journal: matches.is_present("history-journal"),
+        history_limit: parse_history_limit(matches.value_of("history-limit").unwrap()).unwrap(),
     }
 }
@@ -0,0 +1,69 @@
+//! Errors from loading, saving and exporting glyphs. The GUI shows these in a prompt so the
+//! session survives them; headless modes print them and exit with `exit_code()`.
+
+use derive_more::Display;
+
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Display)]
+pub enum MFEKglifError {
+    #[display(fmt = "No file given")]
+    NoFileChosen,
+    #[display(fmt = "Bad glob {:?}: {}", pattern, reason)]
+    BadGlob { pattern: String, reason: String },
+    #[display(fmt = "Could not read .glif {:?}: {}", path, reason)]
+    GlifRead { path: PathBuf, reason: String },
+    #[display(fmt = "Could not write .glif {:?}: {}", path, reason)]
+    GlifWrite { path: PathBuf, reason: String },
+    #[display(fmt = "Could not read {:?}: {}", path, reason)]
+    PlistRead { path: PathBuf, reason: String },
+    #[display(fmt = "Could not write {:?}: {}", path, reason)]
+    PlistWrite { path: PathBuf, reason: String },
+    #[display(fmt = "Could not create directory {:?}: {}", path, error)]
+    CreateDir { path: PathBuf, error: io::Error },
+    #[display(fmt = "UFO {:?} has no glyphs", _0)]
+    EmptyUfo(PathBuf),
+    #[display(fmt = "Glyphs with unsaved changes would be lost: {}; save them first", _0)]
+    UnsavedGlyphs(String),
+    #[display(fmt = "Glyph has {} layers; it must be in a UFO to be exported", _0)]
+    NoParentUfo(usize),
+    #[display(fmt = "Layer {} out of range, glyph has {} layers", layer, count)]
+    LayerOutOfRange { layer: usize, count: usize },
+    #[display(fmt = "Could not render {:?}: {}", path, reason)]
+    Render { path: PathBuf, reason: String },
+    #[display(fmt = "Could not write {:?}: {}", path, error)]
+    RenderWrite { path: PathBuf, error: io::Error },
+    #[display(fmt = "Could not watch {:?}: {}", path, reason)]
+    Watch { path: PathBuf, reason: String },
+    #[display(fmt = "Check found {} error(s)", _0)]
+    CheckFailed(usize),
+    #[display(fmt = "Panicked while working on {:?}", _0)]
+    Panicked(PathBuf),
+    #[display(fmt = "Script {:?} failed: {}", path, reason)]
+    Script { path: PathBuf, reason: String },
+}
+
+impl std::error::Error for MFEKglifError {}
+
+// Loosely follows BSD sysexits.h, so shell scripts can tell a bad glyph from a bad disk. A script
+// failing is the script's own business, so that gets the generic code.
+const EX_SCRIPT: i32 = 1;
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_SOFTWARE: i32 = 70;
+const EX_OSERR: i32 = 71;
+const EX_CANTCREAT: i32 = 73;
+
+impl MFEKglifError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MFEKglifError::NoFileChosen | MFEKglifError::BadGlob { .. } | MFEKglifError::LayerOutOfRange { .. } | MFEKglifError::UnsavedGlyphs(_) => EX_USAGE,
+            MFEKglifError::GlifRead { .. } | MFEKglifError::PlistRead { .. } | MFEKglifError::NoParentUfo(_) | MFEKglifError::EmptyUfo(_) | MFEKglifError::CheckFailed(_) => EX_DATAERR,
+            MFEKglifError::GlifWrite { .. } | MFEKglifError::PlistWrite { .. } | MFEKglifError::CreateDir { .. } | MFEKglifError::RenderWrite { .. } => EX_CANTCREAT,
+            MFEKglifError::Panicked(_) | MFEKglifError::Render { .. } => EX_SOFTWARE,
+            MFEKglifError::Watch { .. } => EX_OSERR,
+            MFEKglifError::Script { .. } => EX_SCRIPT,
+        }
+    }
+}
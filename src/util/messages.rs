@@ -0,0 +1,93 @@
+//! Progress reports from loading and exporting. By default they go to the log like everything else;
+//! with `--message-format=json`, each is printed to stdout as a JSON object on its own line, for
+//! build tools that need to know what happened without parsing log text.
+
+use serde_json::json;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::editor::check::Finding;
+use crate::util::error::MFEKglifError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_message_format(format: MessageFormat) {
+    JSON.store(format == MessageFormat::Json, Ordering::Relaxed);
+}
+
+pub enum Message<'a> {
+    Loaded { path: &'a Path },
+    /// Layer groups were flattened, leaving `layers` layers to write.
+    Rebuilt { glyph: &'a str, layers: usize },
+    LayerExported { glyph: &'a str, layer: usize, name: &'a str, path: &'a Path },
+    LayerInfoWritten { glyph: &'a str, layer: usize, path: &'a Path },
+    PlistMerged { path: &'a Path },
+    Rendered { path: &'a Path },
+    /// One glyph of a batch export is done.
+    GlyphExported { path: &'a Path, layers: usize },
+    GlyphFailed { path: &'a Path, error: &'a MFEKglifError },
+    BatchFinished { exported: usize, total: usize },
+    Finding { finding: &'a Finding },
+    CheckFinished { errors: usize, warnings: usize },
+    Warning { message: String },
+    Error { error: &'a MFEKglifError },
+}
+
+pub fn emit(message: Message) {
+    if JSON.load(Ordering::Relaxed) {
+        println!("{}", to_json(&message));
+    } else {
+        log_human(&message);
+    }
+}
+
+fn to_json(message: &Message) -> serde_json::Value {
+    match message {
+        Message::Loaded { path } => json!({"event": "loaded", "path": path}),
+        Message::Rebuilt { glyph, layers } => json!({"event": "rebuilt", "glyph": glyph, "layers": layers}),
+        Message::LayerExported { glyph, layer, name, path } => json!({"event": "layer_exported", "glyph": glyph, "layer": layer, "name": name, "path": path}),
+        Message::LayerInfoWritten { glyph, layer, path } => json!({"event": "layerinfo_written", "glyph": glyph, "layer": layer, "path": path}),
+        Message::PlistMerged { path } => json!({"event": "plist_merged", "path": path}),
+        Message::Rendered { path } => json!({"event": "rendered", "path": path}),
+        Message::GlyphExported { path, layers } => json!({"event": "glyph_exported", "path": path, "layers": layers}),
+        Message::GlyphFailed { path, error } => json!({"event": "glyph_failed", "path": path, "message": error.to_string(), "code": error.exit_code()}),
+        Message::BatchFinished { exported, total } => json!({"event": "batch_finished", "exported": exported, "total": total}),
+        Message::Finding { finding } => json!({
+            "event": "finding",
+            "severity": finding.severity.to_string(),
+            "layer": finding.location.layer,
+            "contour": finding.location.contour,
+            "point": finding.location.point,
+            "message": finding.message,
+        }),
+        Message::CheckFinished { errors, warnings } => json!({"event": "check_finished", "errors": errors, "warnings": warnings}),
+        Message::Warning { message } => json!({"event": "warning", "message": message}),
+        Message::Error { error } => json!({"event": "error", "message": error.to_string(), "code": error.exit_code()}),
+    }
+}
+
+fn log_human(message: &Message) {
+    match message {
+        Message::Loaded { path } => log::info!("Loaded {:?}", path),
+        Message::Rebuilt { glyph, layers } => log::info!("Flattened glyph {} into {} layer(s)", glyph, layers),
+        Message::LayerExported { glyph, layer, name, path } => log::info!("Wrote layer {} ({}) of glyph {} to {:?}", layer, name, glyph, path),
+        Message::LayerInfoWritten { glyph, layer, path } => log::info!("Wrote layer {} of glyph {}'s layerinfo.plist to {:?}", layer, glyph, path),
+        Message::PlistMerged { path } => log::info!("Wrote {:?}", path),
+        Message::Rendered { path } => log::info!("Rendered glyph to {:?}", path),
+        // Batch and check results are the point of those runs, so they go to stdout rather than the log.
+        Message::GlyphExported { path, layers } => println!("OK\t{}\t{} layer(s)", path.display(), layers),
+        Message::GlyphFailed { path, error } => println!("FAILED\t{}\t{}", path.display(), error),
+        Message::BatchFinished { exported, total } => println!("Exported {} of {} glyph(s).", exported, total),
+        Message::Finding { finding } => println!("{}", finding),
+        Message::CheckFinished { errors, warnings } => println!("{} error(s), {} warning(s).", errors, warnings),
+        Message::Warning { message } => log::warn!("{}", message),
+        Message::Error { error } => log::error!("{}", error),
+    }
+}
@@ -1,6 +1,8 @@
 // Utilities
 pub mod argparser;
+pub mod error;
 pub mod math;
+pub mod messages;
 
 use std::env;
 